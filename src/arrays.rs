@@ -1,6 +1,6 @@
 use std::{marker::PhantomData, mem::MaybeUninit};
 
-use serde::{de::Visitor, ser::SerializeTuple, Deserialize, Deserializer, Serialize, Serializer};
+use serde::{de::Visitor, ser::SerializeSeq, Deserialize, Deserializer, Serialize, Serializer};
 
 struct ArrayVisitor<T, const N: usize> {
     _phantom: PhantomData<T>,
@@ -50,7 +50,6 @@ where
         }
 
         let ret: [T; N] = unsafe { std::mem::transmute_copy(&arr) };
-        std::mem::forget(arr);
 
         Ok(FixedSizeArray::from(ret))
     }
@@ -67,6 +66,12 @@ impl<T, const N: usize> From<[T; N]> for FixedSizeArray<T, N> {
     }
 }
 
+impl<T: Default, const N: usize> Default for FixedSizeArray<T, N> {
+    fn default() -> Self {
+        Self::from(std::array::from_fn(|_| T::default()))
+    }
+}
+
 impl<'de, T, const N: usize> Deserialize<'de> for FixedSizeArray<T, N>
 where
     T: Deserialize<'de>,
@@ -92,7 +97,7 @@ where
     where
         S: Serializer,
     {
-        let mut s = serializer.serialize_tuple(N)?;
+        let mut s = serializer.serialize_seq(Some(N))?;
         for value in &self.value {
             s.serialize_element(value)?;
         }
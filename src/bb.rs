@@ -2,7 +2,7 @@ use crate::vec::Vec3I;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Deserialize, Serialize)]
-pub struct BoundingBox(#[serde(serialize_with = "nbt::int_array")] [i32; 6]);
+pub struct BoundingBox(#[serde(serialize_with = "nbt::i32_array")] [i32; 6]);
 
 impl BoundingBox {
     pub fn new(min_pos: Vec3I, max_pos: Vec3I) -> Self {
@@ -23,4 +23,11 @@ impl BoundingBox {
     pub fn max_pos(&self) -> Vec3I {
         Vec3I::new(self.0[3], self.0[4], self.0[5])
     }
+
+    /// Whether `pos` lies inside the box, both corners are inclusive.
+    pub fn contains(&self, pos: &Vec3I) -> bool {
+        (self.0[0]..=self.0[3]).contains(&pos.x())
+            && (self.0[1]..=self.0[4]).contains(&pos.y())
+            && (self.0[2]..=self.0[5]).contains(&pos.z())
+    }
 }
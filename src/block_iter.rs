@@ -0,0 +1,286 @@
+use std::{error::Error, fmt, str::FromStr};
+
+use crate::{
+    block_state::BlockState,
+    error::McaError,
+    pos::BlockPos,
+    region::{BlockStatePalette, RegionChunk, Section},
+};
+
+/// A block name with an optional subset of its properties, written the same way as in the
+/// game's commands, e.g. `minecraft:chest[type=single]`. A state matches if it has the same
+/// name and every listed property has the listed value, other properties can be anything.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockPattern {
+    // Only holds the properties the pattern lists.
+    state: BlockState,
+}
+
+impl BlockPattern {
+    /// Matches every state of the block named `name`, the `minecraft:` namespace can be left out.
+    pub fn new(name: &str) -> Self {
+        Self {
+            state: BlockState::new(name),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        self.state.name()
+    }
+
+    /// The block name and the properties a state needs to match.
+    pub fn state(&self) -> &BlockState {
+        &self.state
+    }
+
+    pub fn matches(&self, state: &BlockState) -> bool {
+        self.state.is_subset_of(state)
+    }
+
+    /// Same as `matches` for a palette entry, comparing the strings without converting it.
+    pub fn matches_palette(&self, state: &BlockStatePalette) -> bool {
+        state.name() == self.state.name()
+            && self
+                .state
+                .properties()
+                .all(|(name, value)| state.property(name) == Some(value))
+    }
+}
+
+impl FromStr for BlockPattern {
+    type Err = InvalidBlockPattern;
+
+    fn from_str(pattern: &str) -> Result<Self, Self::Err> {
+        let state = pattern
+            .parse()
+            .map_err(|_| InvalidBlockPattern(pattern.to_string()))?;
+        Ok(Self { state })
+    }
+}
+
+/// Returned when a block pattern such as `minecraft:chest[type=single]` can't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidBlockPattern(String);
+
+impl fmt::Display for InvalidBlockPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid block pattern: {}", self.0)
+    }
+}
+
+impl Error for InvalidBlockPattern {}
+
+/// Narrows down which blocks an iterator yields. Sections that can't contain a match, because
+/// none of their palette entries match or they are outside of the Y range, are skipped without
+/// decoding them.
+#[derive(Debug, Clone, Default)]
+pub struct BlockFilter {
+    // Any block matches if empty.
+    patterns: Vec<BlockPattern>,
+    min_y: Option<i32>,
+    max_y: Option<i32>,
+}
+
+impl BlockFilter {
+    /// A filter that matches every block.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also match blocks named `name`, the `minecraft:` namespace can be left out.
+    pub fn with_name(self, name: &str) -> Self {
+        self.with_pattern(BlockPattern::new(name))
+    }
+
+    /// Also match blocks matching `pattern`.
+    pub fn with_pattern(mut self, pattern: BlockPattern) -> Self {
+        self.patterns.push(pattern);
+        self
+    }
+
+    /// Only match blocks from `min_y` to `max_y` inclusive.
+    pub fn with_y_range(mut self, min_y: i32, max_y: i32) -> Self {
+        self.min_y = Some(min_y);
+        self.max_y = Some(max_y);
+        self
+    }
+
+    pub fn matches_state(&self, state: &BlockState) -> bool {
+        self.patterns.is_empty() || self.patterns.iter().any(|pattern| pattern.matches(state))
+    }
+
+    pub fn matches_y(&self, y: i32) -> bool {
+        self.min_y.is_none_or(|min_y| y >= min_y) && self.max_y.is_none_or(|max_y| y <= max_y)
+    }
+
+    /// Same as `matches_state` for a palette entry.
+    pub fn matches_palette(&self, state: &BlockStatePalette) -> bool {
+        self.patterns.is_empty()
+            || self
+                .patterns
+                .iter()
+                .any(|pattern| pattern.matches_palette(state))
+    }
+
+    pub fn matches(&self, pos: &BlockPos, state: &BlockStatePalette) -> bool {
+        self.matches_y(pos.y()) && self.matches_palette(state)
+    }
+
+    // Checks the Y range and palette of a section, returning which palette entries match if any.
+    fn section_matches(&self, section: &Section) -> Option<Vec<bool>> {
+        let bottom = section.y() as i32 * 16;
+        if self.max_y.is_some_and(|max_y| bottom > max_y)
+            || self.min_y.is_some_and(|min_y| bottom + 15 < min_y)
+        {
+            return None;
+        }
+        let palette_matches: Vec<bool> = section
+            .block_palette()
+            .iter()
+            .map(|state| self.matches_palette(state))
+            .collect();
+        if palette_matches.iter().any(|matches| *matches) {
+            Some(palette_matches)
+        } else {
+            None
+        }
+    }
+}
+
+/// Iterator over the blocks of a chunk, created by `RegionChunk::blocks`.
+pub struct Blocks<'a> {
+    chunk: &'a RegionChunk,
+    filter: BlockFilter,
+    sections: std::slice::Iter<'a, Section>,
+    current: Option<CurrentSection<'a>>,
+}
+
+struct CurrentSection<'a> {
+    section: &'a Section,
+    indices: Box<[u16; 4096]>,
+    palette_matches: Vec<bool>,
+    index: usize,
+}
+
+impl<'a> Blocks<'a> {
+    pub(crate) fn new(chunk: &'a RegionChunk, filter: BlockFilter) -> Self {
+        Self {
+            chunk,
+            filter,
+            sections: chunk.sections().iter(),
+            current: None,
+        }
+    }
+}
+
+impl<'a> Iterator for Blocks<'a> {
+    type Item = (BlockPos, &'a BlockStatePalette);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(current) = &mut self.current {
+                while current.index < 4096 {
+                    let index = current.index;
+                    current.index += 1;
+                    let palette_index = current.indices[index] as usize;
+                    if !current
+                        .palette_matches
+                        .get(palette_index)
+                        .copied()
+                        .unwrap_or(false)
+                    {
+                        continue;
+                    }
+                    let chunk = self.chunk.pos();
+                    let pos = BlockPos::new(
+                        chunk.x() * 16 + (index % 16) as i32,
+                        current.section.y() as i32 * 16 + (index / 256) as i32,
+                        chunk.z() * 16 + ((index / 16) % 16) as i32,
+                    );
+                    if self.filter.matches_y(pos.y()) {
+                        let section: &'a Section = current.section;
+                        return Some((pos, &section.block_palette()[palette_index]));
+                    }
+                }
+            }
+            let section = self.sections.next()?;
+            self.current =
+                self.filter
+                    .section_matches(section)
+                    .map(|palette_matches| CurrentSection {
+                        section,
+                        indices: Box::new(section.decode()),
+                        palette_matches,
+                        index: 0,
+                    });
+        }
+    }
+}
+
+/// Iterator over the matching blocks of many chunks, such as a whole region file or
+/// dimension. Since each chunk is dropped once it has been searched, states are cloned.
+pub struct ChunksBlocks<I> {
+    chunks: I,
+    filter: BlockFilter,
+    current: std::vec::IntoIter<(BlockPos, BlockStatePalette)>,
+}
+
+impl<I> ChunksBlocks<I> {
+    pub(crate) fn new(chunks: I, filter: BlockFilter) -> Self {
+        Self {
+            chunks,
+            filter,
+            current: Vec::new().into_iter(),
+        }
+    }
+}
+
+impl<I> Iterator for ChunksBlocks<I>
+where
+    I: Iterator<Item = Result<RegionChunk, McaError>>,
+{
+    type Item = Result<(BlockPos, BlockStatePalette), McaError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(block) = self.current.next() {
+                return Some(Ok(block));
+            }
+            match self.chunks.next()? {
+                Ok(chunk) => {
+                    self.current = chunk
+                        .blocks_matching(self.filter.clone())
+                        .map(|(pos, state)| (pos, state.clone()))
+                        .collect::<Vec<_>>()
+                        .into_iter()
+                }
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
+
+#[test]
+fn test_block_pattern() {
+    use std::collections::HashMap;
+
+    let chest = BlockStatePalette::with_properties(
+        "minecraft:chest",
+        HashMap::from([
+            ("type".to_string(), "single".to_string()),
+            ("facing".to_string(), "north".to_string()),
+        ]),
+    );
+    let chest = BlockState::from(&chest);
+    let pattern: BlockPattern = "chest[type=single]".parse().unwrap();
+    assert_eq!(pattern.name(), "minecraft:chest");
+    assert!(pattern.matches(&chest));
+    assert!(pattern.matches_palette(&BlockStatePalette::from(&chest)));
+    assert!(!pattern.matches_palette(&BlockStatePalette::new("minecraft:chest")));
+    assert!(!"minecraft:chest[type=left]"
+        .parse::<BlockPattern>()
+        .unwrap()
+        .matches(&chest));
+    assert!("minecraft:chest[type".parse::<BlockPattern>().is_err());
+    assert!("[type=single]".parse::<BlockPattern>().is_err());
+}
@@ -0,0 +1,257 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt,
+    hash::{Hash, Hasher},
+    str::FromStr,
+    sync::Arc,
+};
+
+use crate::region::BlockStatePalette;
+
+/// A shared string, cloning it is cheap and comparing clones of the same symbol only looks at
+/// the pointer.
+#[derive(Clone)]
+pub struct Symbol(Arc<str>);
+
+impl Symbol {
+    // Symbols aren't interned in a global table, which would keep every name ever read from a
+    // world alive. States only share the symbols they're cloned from.
+    pub(crate) fn new(value: &str) -> Self {
+        Self(value.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0) || self.0 == other.0
+    }
+}
+
+impl Eq for Symbol {}
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// A block state with a shared name and properties sorted by name, so two states are equal no
+/// matter which order their properties were given in. Cloning and comparing clones are cheap.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BlockState {
+    name: Symbol,
+    properties: Arc<[(Symbol, Symbol)]>,
+}
+
+impl BlockState {
+    /// A state without properties, the `minecraft:` namespace can be left out of `name`.
+    pub fn new(name: &str) -> Self {
+        let name = if name.contains(':') {
+            Symbol::new(name)
+        } else {
+            Symbol::new(&format!("minecraft:{}", name))
+        };
+        Self {
+            name,
+            properties: Arc::new([]),
+        }
+    }
+
+    // Sorts the properties once, rather than once per property like `with_property`. A property
+    // listed more than once keeps its last value.
+    fn with_properties<'a, I>(name: &str, properties: I) -> Self
+    where
+        I: IntoIterator<Item = (&'a str, &'a str)>,
+    {
+        let mut properties: Vec<(Symbol, Symbol)> = properties
+            .into_iter()
+            .map(|(name, value)| (Symbol::new(name), Symbol::new(value)))
+            .collect();
+        // The sort is stable, so after reversing the last value of a property comes first.
+        properties.reverse();
+        properties.sort_by(|(name, _), (other, _)| name.as_str().cmp(other.as_str()));
+        properties.dedup_by(|(name, _), (other, _)| name == other);
+        Self {
+            properties: properties.into(),
+            ..Self::new(name)
+        }
+    }
+
+    /// Sets a property, replacing its previous value.
+    pub fn with_property(self, name: &str, value: &str) -> Self {
+        let (name, value) = (Symbol::new(name), Symbol::new(value));
+        let mut properties: Vec<_> = self
+            .properties
+            .iter()
+            .filter(|(other, _)| *other != name)
+            .cloned()
+            .collect();
+        properties.push((name, value));
+        properties.sort_by(|(name, _), (other, _)| name.as_str().cmp(other.as_str()));
+        Self {
+            name: self.name,
+            properties: properties.into(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    pub fn name_symbol(&self) -> &Symbol {
+        &self.name
+    }
+
+    pub fn property(&self, name: &str) -> Option<&str> {
+        self.properties
+            .iter()
+            .find(|(property, _)| property.as_str() == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Properties sorted by name.
+    pub fn properties(&self) -> impl Iterator<Item = (&str, &str)> + '_ {
+        self.properties
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    /// Whether `other` has the same name and every property of this state, other properties of
+    /// `other` can be anything.
+    pub fn is_subset_of(&self, other: &BlockState) -> bool {
+        self.name == other.name
+            && self
+                .properties
+                .iter()
+                .all(|property| other.properties.contains(property))
+    }
+}
+
+impl FromStr for BlockState {
+    type Err = InvalidBlockState;
+
+    /// Parses the game's syntax, e.g. `minecraft:oak_stairs[facing=north,half=top]`.
+    fn from_str(state: &str) -> Result<Self, Self::Err> {
+        let (name, properties) =
+            parse_state(state).ok_or_else(|| InvalidBlockState(state.to_string()))?;
+        Ok(Self::with_properties(name, properties))
+    }
+}
+
+impl fmt::Display for BlockState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if !self.properties.is_empty() {
+            write!(f, "[")?;
+            for (i, (name, value)) in self.properties.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ",")?;
+                }
+                write!(f, "{}={}", name, value)?;
+            }
+            write!(f, "]")?;
+        }
+        Ok(())
+    }
+}
+
+impl From<&BlockStatePalette> for BlockState {
+    fn from(state: &BlockStatePalette) -> Self {
+        let properties = state.properties().into_iter().flatten();
+        Self::with_properties(
+            state.name(),
+            properties.map(|(name, value)| (name.as_str(), value.as_str())),
+        )
+    }
+}
+
+impl From<&BlockState> for BlockStatePalette {
+    fn from(state: &BlockState) -> Self {
+        let properties: HashMap<String, String> = state
+            .properties()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        BlockStatePalette::with_properties(state.name(), properties)
+    }
+}
+
+/// Returned when a block state such as `minecraft:oak_stairs[facing=north]` can't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidBlockState(String);
+
+impl fmt::Display for InvalidBlockState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid block state: {}", self.0)
+    }
+}
+
+impl Error for InvalidBlockState {}
+
+// Splits `name[property=value,...]` into the name and its properties.
+pub(crate) fn parse_state(state: &str) -> Option<(&str, Vec<(&str, &str)>)> {
+    let (name, properties) = match state.split_once('[') {
+        Some((name, properties)) => (name, Some(properties.strip_suffix(']')?)),
+        None => (state, None),
+    };
+    let name = name.trim();
+    if name.is_empty() || name.contains(']') {
+        return None;
+    }
+    let mut parsed = Vec::new();
+    for property in properties
+        .into_iter()
+        .flat_map(|properties| properties.split(','))
+        .filter(|property| !property.trim().is_empty())
+    {
+        let (name, value) = property.split_once('=')?;
+        let (name, value) = (name.trim(), value.trim());
+        if name.is_empty() || value.is_empty() {
+            return None;
+        }
+        parsed.push((name, value));
+    }
+    Some((name, parsed))
+}
+
+#[test]
+fn test_block_state() {
+    let state: BlockState = "oak_stairs[half=top,facing=north]".parse().unwrap();
+    assert_eq!(state.name(), "minecraft:oak_stairs");
+    assert_eq!(state.property("facing"), Some("north"));
+    assert_eq!(
+        state.to_string(),
+        "minecraft:oak_stairs[facing=north,half=top]"
+    );
+    let same = BlockState::new("minecraft:oak_stairs")
+        .with_property("facing", "north")
+        .with_property("half", "top");
+    assert_eq!(state, same);
+    assert_eq!(BlockState::from(&BlockStatePalette::from(&state)), state);
+    assert!("oak_stairs[facing".parse::<BlockState>().is_err());
+    // The last value of a repeated property wins, like with `with_property`.
+    let repeated: BlockState = "oak_stairs[half=bottom,facing=north,half=top]"
+        .parse()
+        .unwrap();
+    assert_eq!(repeated, state);
+    assert!(BlockState::new("oak_stairs")
+        .with_property("half", "top")
+        .is_subset_of(&state));
+    assert!(!state.is_subset_of(&BlockState::new("oak_stairs")));
+}
@@ -0,0 +1,318 @@
+// Block classification used when rebuilding heightmaps and light. This only knows about vanilla blocks by
+// name and approximates the game's collision shapes, modded blocks are treated as solid.
+
+use crate::region::BlockStatePalette;
+
+// Blocks without a collision box, which mobs and rain fall through.
+const NON_SOLID_BLOCKS: &[&str] = &[
+    "air",
+    "cave_air",
+    "void_air",
+    "water",
+    "lava",
+    "bubble_column",
+    "light",
+    "structure_void",
+    "fire",
+    "soul_fire",
+    "cobweb",
+    "grass",
+    "short_grass",
+    "tall_grass",
+    "fern",
+    "large_fern",
+    "dead_bush",
+    "dandelion",
+    "poppy",
+    "blue_orchid",
+    "allium",
+    "azure_bluet",
+    "oxeye_daisy",
+    "cornflower",
+    "lily_of_the_valley",
+    "wither_rose",
+    "torchflower",
+    "sunflower",
+    "lilac",
+    "rose_bush",
+    "peony",
+    "pitcher_plant",
+    "pink_petals",
+    "spore_blossom",
+    "hanging_roots",
+    "glow_lichen",
+    "sculk_vein",
+    "brown_mushroom",
+    "red_mushroom",
+    "crimson_fungus",
+    "warped_fungus",
+    "crimson_roots",
+    "warped_roots",
+    "nether_sprouts",
+    "vine",
+    "sugar_cane",
+    "kelp",
+    "kelp_plant",
+    "seagrass",
+    "tall_seagrass",
+    "sea_pickle",
+    "wheat",
+    "carrots",
+    "potatoes",
+    "beetroots",
+    "melon_stem",
+    "pumpkin_stem",
+    "attached_melon_stem",
+    "attached_pumpkin_stem",
+    "torchflower_crop",
+    "pitcher_crop",
+    "sweet_berry_bush",
+    "nether_wart",
+    "cocoa",
+    "bamboo_sapling",
+    "redstone_wire",
+    "tripwire",
+    "tripwire_hook",
+    "lever",
+    "ladder",
+    "snow",
+    "nether_portal",
+    "end_portal",
+    "end_gateway",
+];
+
+// Name suffixes shared by whole families of non-solid blocks.
+const NON_SOLID_SUFFIXES: &[&str] = &[
+    "_sapling",
+    "_tulip",
+    "_vines",
+    "_vines_plant",
+    "torch",
+    "rail",
+    "_button",
+    "_pressure_plate",
+    "_sign",
+    "_banner",
+    "_carpet",
+    "_coral",
+    "_coral_fan",
+    "_coral_wall_fan",
+];
+
+// Blocks that are always full of water.
+const WATER_PLANTS: &[&str] = &[
+    "kelp",
+    "kelp_plant",
+    "seagrass",
+    "tall_seagrass",
+    "bubble_column",
+];
+
+pub(crate) fn is_air(state: &BlockStatePalette) -> bool {
+    matches!(short_name(state.name()), "air" | "cave_air" | "void_air")
+}
+
+/// Whether mobs collide with the block, i.e. it has a collision box.
+pub(crate) fn blocks_motion(state: &BlockStatePalette) -> bool {
+    let name = short_name(state.name());
+    !NON_SOLID_BLOCKS.contains(&name)
+        && !NON_SOLID_SUFFIXES
+            .iter()
+            .any(|suffix| name.ends_with(suffix))
+}
+
+/// Whether the block holds water or lava, including waterlogged blocks.
+pub(crate) fn has_fluid(state: &BlockStatePalette) -> bool {
+    let name = short_name(state.name());
+    name == "water"
+        || name == "lava"
+        || WATER_PLANTS.contains(&name)
+        || state.property("waterlogged") == Some("true")
+}
+
+pub(crate) fn is_leaves(state: &BlockStatePalette) -> bool {
+    short_name(state.name()).ends_with("_leaves")
+}
+
+// Blocks that let light through without being a full solid cube.
+const TRANSPARENT_BLOCKS: &[&str] = &[
+    "glass",
+    "glass_pane",
+    "ice",
+    "frosted_ice",
+    "barrier",
+    "beacon",
+    "conduit",
+    "iron_bars",
+    "chain",
+    "lantern",
+    "soul_lantern",
+    "chest",
+    "trapped_chest",
+    "ender_chest",
+    "bell",
+    "anvil",
+    "chipped_anvil",
+    "damaged_anvil",
+    "enchanting_table",
+    "brewing_stand",
+    "cauldron",
+    "water_cauldron",
+    "lava_cauldron",
+    "powder_snow_cauldron",
+    "hopper",
+    "flower_pot",
+    "end_rod",
+    "lightning_rod",
+    "scaffolding",
+    "slime_block",
+    "honey_block",
+    "mangrove_roots",
+    "dirt_path",
+    "farmland",
+    "daylight_detector",
+    "repeater",
+    "comparator",
+    "candle",
+    "campfire",
+    "soul_campfire",
+    "lectern",
+    "stonecutter",
+    "grindstone",
+    "sea_pickle",
+    "turtle_egg",
+    "sniffer_egg",
+    "dragon_egg",
+    "pointed_dripstone",
+    "amethyst_cluster",
+    "large_amethyst_bud",
+    "medium_amethyst_bud",
+    "small_amethyst_bud",
+    "big_dripleaf",
+    "small_dripleaf",
+    "azalea",
+    "flowering_azalea",
+    "cactus",
+    "bamboo",
+    "cake",
+    "end_portal_frame",
+    "decorated_pot",
+];
+
+const TRANSPARENT_SUFFIXES: &[&str] = &[
+    "_glass",
+    "_glass_pane",
+    "_slab",
+    "_stairs",
+    "_fence",
+    "_fence_gate",
+    "_wall",
+    "_door",
+    "_trapdoor",
+    "_bed",
+    "_candle",
+    "_head",
+    "_skull",
+    "_wall_head",
+    "_wall_skull",
+    "_shulker_box",
+    "shulker_box",
+    "_candle_cake",
+    "_pane",
+];
+
+/// How much light is lost passing through the block, 15 for opaque blocks. Leaves, cobwebs and
+/// water only dim light a little.
+pub(crate) fn light_opacity(state: &BlockStatePalette) -> u8 {
+    let name = short_name(state.name());
+    if name == "tinted_glass" {
+        return 15;
+    }
+    if name == "water" || name == "cobweb" || WATER_PLANTS.contains(&name) || is_leaves(state) {
+        return 1;
+    }
+    if state.property("waterlogged") == Some("true") {
+        return 1;
+    }
+    let transparent = !blocks_motion(state)
+        || TRANSPARENT_BLOCKS.contains(&name)
+        || TRANSPARENT_SUFFIXES
+            .iter()
+            .any(|suffix| name.ends_with(suffix));
+    if transparent {
+        0
+    } else {
+        15
+    }
+}
+
+/// Light level (0-15) the block gives off.
+pub(crate) fn light_emission(state: &BlockStatePalette) -> u8 {
+    let name = short_name(state.name());
+    let lit = state.property("lit") == Some("true");
+    let property_level = |property: &str| {
+        state
+            .property(property)
+            .and_then(|value| value.parse::<u8>().ok())
+            .unwrap_or(0)
+            .min(15)
+    };
+    let emission = match name {
+        "glowstone"
+        | "sea_lantern"
+        | "lava"
+        | "jack_o_lantern"
+        | "beacon"
+        | "conduit"
+        | "shroomlight"
+        | "end_gateway"
+        | "end_portal"
+        | "fire"
+        | "lantern"
+        | "ochre_froglight"
+        | "verdant_froglight"
+        | "pearlescent_froglight"
+        | "lava_cauldron" => 15,
+        "campfire" | "redstone_lamp" if lit => 15,
+        "torch" | "wall_torch" | "end_rod" => 14,
+        "cave_vines" | "cave_vines_plant" if state.property("berries") == Some("true") => 14,
+        "furnace" | "blast_furnace" | "smoker" if lit => 13,
+        "nether_portal" => 11,
+        "soul_torch" | "soul_wall_torch" | "soul_lantern" | "soul_fire" | "crying_obsidian" => 10,
+        "soul_campfire" if lit => 10,
+        "redstone_ore" | "deepslate_redstone_ore" if lit => 9,
+        "enchanting_table" | "ender_chest" | "glow_lichen" => 7,
+        "redstone_torch" | "redstone_wall_torch" if lit => 7,
+        "sculk_catalyst" => 6,
+        "amethyst_cluster" => 5,
+        "large_amethyst_bud" => 4,
+        "magma_block" => 3,
+        "medium_amethyst_bud" => 2,
+        "brewing_stand"
+        | "brown_mushroom"
+        | "dragon_egg"
+        | "end_portal_frame"
+        | "sculk_sensor"
+        | "calibrated_sculk_sensor"
+        | "small_amethyst_bud" => 1,
+        "light" => property_level("level"),
+        "respawn_anchor" => match property_level("charges") {
+            0 => 0,
+            charges => charges * 4 - 1,
+        },
+        "sea_pickle" if state.property("waterlogged") == Some("true") => {
+            property_level("pickles") * 3 + 3
+        }
+        _ if lit && (name == "candle" || name.ends_with("_candle")) => {
+            property_level("candles") * 3
+        }
+        _ if lit && name.ends_with("candle_cake") => 3,
+        _ => 0,
+    };
+    emission.min(15)
+}
+
+// Strips the `minecraft:` namespace, other namespaces are kept so they never match.
+fn short_name(name: &str) -> &str {
+    name.strip_prefix("minecraft:").unwrap_or(name)
+}
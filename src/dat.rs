@@ -0,0 +1,78 @@
+use std::{
+    fs,
+    io::{self, Read},
+    path::Path,
+};
+
+use flate2::bufread::GzDecoder;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    error::McaError,
+    mca::{compress_bytes_with_scheme, CompressionScheme},
+    nbt_serde,
+};
+
+// Reads a standalone gzip compressed NBT file such as level.dat or a player's .dat file.
+pub(crate) fn read_gzip_nbt<T>(path: &Path) -> Result<T, McaError>
+where
+    T: DeserializeOwned,
+{
+    parse_nbt(path, read_gzip_bytes(path)?)
+}
+
+// Decompresses a gzip compressed NBT file without parsing it.
+pub(crate) fn read_gzip_bytes(path: &Path) -> Result<Vec<u8>, McaError> {
+    let io_error = |source| McaError::Io {
+        path: path.to_path_buf(),
+        source,
+    };
+    let bytes = fs::read(path).map_err(io_error)?;
+    let mut decompressed = Vec::new();
+    GzDecoder::new(&bytes[..])
+        .read_to_end(&mut decompressed)
+        .map_err(io_error)?;
+    Ok(decompressed)
+}
+
+pub(crate) fn parse_nbt<T>(path: &Path, bytes: Vec<u8>) -> Result<T, McaError>
+where
+    T: DeserializeOwned,
+{
+    nbt_serde::from_bytes(&bytes).map_err(|source| McaError::InvalidFile {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+// Writes a gzip compressed NBT file. The data goes to a temporary file first which then
+// replaces the original, so a crash halfway through never leaves a corrupt file behind.
+pub(crate) fn write_gzip_nbt<T>(path: &Path, value: &T) -> Result<(), McaError>
+where
+    T: Serialize,
+{
+    let bytes = nbt_serde::to_bytes(value).map_err(|source| McaError::InvalidFile {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let temporary_path = path.with_extension("dat.tmp");
+    compress_bytes_with_scheme(&bytes, CompressionScheme::Gzip)
+        .and_then(|compressed| fs::write(&temporary_path, compressed))
+        .and_then(|_| fs::rename(&temporary_path, path))
+        .map_err(|source: io::Error| McaError::Io {
+            path: path.to_path_buf(),
+            source,
+        })
+}
+
+#[test]
+fn test_gzip_nbt_round_trip() {
+    use std::collections::HashMap;
+
+    let path = crate::TempPath::new("test.dat");
+    let value: HashMap<String, i32> = [("Count".to_string(), 3)].into_iter().collect();
+    write_gzip_nbt(&path, &value).unwrap();
+    assert!(!path.with_extension("dat.tmp").exists());
+    let read: HashMap<String, i32> = read_gzip_nbt(&path).unwrap();
+    assert_eq!(read, value);
+}
@@ -1,6 +1,11 @@
+// Most of the entity formats below aren't read anywhere yet.
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 
-use crate::{vec::{Vec3I, Vec3D, Vec2F, Vec2I}, uuid::Uuid};
+use crate::{nbt_serde, vec::{Vec3I, Vec3D, Vec2F, Vec2I}, uuid::Uuid};
 
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -16,10 +21,28 @@ pub struct EntityChunk {
 pub struct ItemTag {
     damage: Option<i32>,
     unbreakable: Option<bool>,
-    can_destroy: Vec<String>,
+    can_destroy: Option<Vec<String>>,
     custom_model_data: Option<i32>,
+    enchantments: Option<Vec<Enchantment>>,
+    // Enchantments held by an enchanted book.
+    stored_enchantments: Option<Vec<Enchantment>>,
+    repair_cost: Option<i32>,
+    // Custom name and lore.
+    #[serde(rename = "display", default, with = "nbt_serde::option")]
+    display: Option<nbt::Value>,
+    // Every other tag, e.g. written books or a shulker box's contents.
+    #[serde(rename = "$extra", default, with = "nbt_serde::compound")]
+    extra: HashMap<String, nbt::Value>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Enchantment {
+    id: String,
+    lvl: i16,
 }
 
+/// An item stack in the 1.20.2 to 1.20.4 format (data versions 3578 to 3700), which still
+/// stores its `Count` and a `tag` compound. 1.20.5 replaced them with `count` and components.
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Item {
     #[serde(rename = "Count")]
@@ -30,6 +53,20 @@ pub struct Item {
     tag: Option<ItemTag>
 }
 
+impl Item {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn count(&self) -> i8 {
+        self.count
+    }
+
+    pub fn slot(&self) -> Option<i8> {
+        self.slot
+    }
+}
+
 // TODO: Add the other fields.
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Brain {
@@ -172,8 +209,9 @@ pub struct Entity {
     fire: i16,
     glowing: Option<bool>,
     has_visual_fire: Option<bool>,
+    // Players are the only entities saved without an id.
     #[serde(rename = "id")]
-    id: String,
+    id: Option<String>,
     invulnerable: bool,
     motion: Vec<f64>,
     no_gravity: Option<bool>,
@@ -186,19 +224,51 @@ pub struct Entity {
     ticks_frozen: Option<i32>,
     #[serde(rename = "UUID")]
     uuid: Uuid,
+    // Tags of the specific entity type, which aren't modelled here.
+    #[serde(rename = "$extra", default, with = "nbt_serde::compound")]
+    extra: HashMap<String, nbt::Value>,
 }
 
+impl Entity {
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    pub fn uuid(&self) -> &Uuid {
+        &self.uuid
+    }
+
+    pub fn pos(&self) -> &Vec3D {
+        &self.pos
+    }
+
+    pub fn rotation(&self) -> &Vec2F {
+        &self.rotation
+    }
+
+    pub fn custom_name(&self) -> Option<&str> {
+        self.custom_name.as_deref()
+    }
+
+    // Drops the unmodelled tags for which `keep` returns false.
+    pub(crate) fn retain_extra<F: FnMut(&str) -> bool>(&mut self, mut keep: F) {
+        self.extra.retain(|name, _| keep(name));
+    }
+}
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct HiddenEffect;
 
+/// A status effect in the same 1.20.2 to 1.20.4 format as [`Item`], with a namespaced string
+/// `id` instead of the numeric `Id` of earlier versions.
 #[derive(Debug, Deserialize, Serialize)]
 pub struct PotionEffect {
     ambient: bool,
     amplifier: i8,
     duration: i32,
     hidden_effect: Option<HiddenEffect>,
-    id: i32,
+    // Namespaced effect id, e.g. minecraft:speed.
+    id: String,
     show_icon: bool,
     show_particles: bool,
 }
@@ -458,4 +528,48 @@ pub struct BlockEntity {
     x: i32,
     y: i32,
     z: i32,
-}
\ No newline at end of file
+    // The tags of the specific block entity, e.g. a chest's items or a sign's text.
+    #[serde(rename = "$extra", default, with = "nbt_serde::compound")]
+    extra: HashMap<String, nbt::Value>,
+}
+#[test]
+fn test_entity_round_trip() {
+    use nbt::Value;
+
+    fn compound(tags: Vec<(&str, Value)>) -> Value {
+        Value::Compound(tags.into_iter().map(|(name, value)| (name.to_string(), value)).collect())
+    }
+
+    let cow = compound(vec![
+        ("id", Value::String("minecraft:cow".to_string())),
+        ("Air", Value::Short(300)),
+        ("FallDistance", Value::Float(0.0)),
+        ("Fire", Value::Short(-1)),
+        ("Invulnerable", Value::Byte(0)),
+        ("Motion", Value::List(vec![Value::Double(0.0), Value::Double(-0.08), Value::Double(0.0)])),
+        ("OnGround", Value::Byte(1)),
+        ("PortalCooldown", Value::Int(0)),
+        ("Pos", Value::List(vec![Value::Double(1.5), Value::Double(64.0), Value::Double(-3.5)])),
+        ("Rotation", Value::List(vec![Value::Float(45.0), Value::Float(0.0)])),
+        ("UUID", Value::IntArray(vec![1, -2, 3, -4])),
+        // Cow tags, which `Entity` doesn't model.
+        ("PersistenceRequired", Value::Byte(1)),
+        ("LoveCause", Value::IntArray(vec![5, 6, 7, 8])),
+        ("Brain", compound(vec![("memories", compound(vec![]))])),
+    ]);
+    let chunk = compound(vec![
+        ("DataVersion", Value::Int(3700)),
+        ("Entities", Value::List(vec![cow])),
+        ("Position", Value::IntArray(vec![2, -1])),
+    ]);
+
+    let entities: EntityChunk = nbt_serde::from_value(chunk.clone()).unwrap();
+    let entity = &entities.entities[0];
+    assert_eq!(entity.id(), Some("minecraft:cow"));
+    assert!(entity.on_ground);
+    assert_eq!(*entity.uuid(), Uuid::from([1, -2, 3, -4]));
+    assert_eq!(entity.extra["PersistenceRequired"], Value::Byte(1));
+    assert_eq!((entities.position.x(), entities.position.y()), (2, -1));
+    // Bools are written back as bytes and the int arrays stay int arrays.
+    assert_eq!(nbt_serde::to_value(&entities).unwrap(), chunk);
+}
@@ -0,0 +1,210 @@
+use std::{fmt, io, path::PathBuf};
+
+use crate::{mca::UnsupportedCompression, pos::ChunkPos};
+
+/// Everything that can go wrong while reading or writing region files and the other files of a world.
+#[derive(Debug)]
+pub enum McaError {
+    // The file couldn't be opened, read or written.
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    // The file is too small to hold the 8KiB location and timestamp tables.
+    TruncatedHeader {
+        path: PathBuf,
+    },
+    // Region-local chunk coordinates that lie outside of the 32x32 region.
+    ChunkOutOfRange {
+        path: PathBuf,
+        local_x: usize,
+        local_z: usize,
+    },
+    // The location table or the chunk header points outside of the file.
+    SectorOutOfBounds {
+        path: PathBuf,
+        chunk: ChunkPos,
+    },
+    // The chunk uses a compression scheme that can't be decompressed.
+    BadCompression {
+        path: PathBuf,
+        chunk: ChunkPos,
+        source: UnsupportedCompression,
+    },
+    // The compressed chunk data is corrupt.
+    Decompress {
+        path: PathBuf,
+        chunk: ChunkPos,
+        source: io::Error,
+    },
+    // The chunk is stored in a .mcc file which couldn't be read or written.
+    ExternalChunk {
+        path: PathBuf,
+        chunk: ChunkPos,
+        source: io::Error,
+    },
+    // The .mcc file of an oversized chunk is named after the absolute chunk coordinates, which
+    // can't be worked out when the region file isn't named `r.<x>.<z>.mca`.
+    UnknownRegionCoordinates {
+        path: PathBuf,
+        chunk: ChunkPos,
+    },
+    // The chunk NBT couldn't be (de)serialized.
+    Nbt {
+        path: PathBuf,
+        chunk: ChunkPos,
+        source: nbt::Error,
+    },
+    // A standalone NBT file such as level.dat couldn't be (de)serialized.
+    InvalidFile {
+        path: PathBuf,
+        source: nbt::Error,
+    },
+    // A JSON file such as a data generator report couldn't be parsed.
+    InvalidJson {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    // The chunk was converted from a layout older than 1.18 when it was read, writing it back
+    // would store the new layout under its old data version.
+    LegacyChunk {
+        path: PathBuf,
+        chunk: ChunkPos,
+    },
+}
+
+impl McaError {
+    /// Path of the file the error occurred in, the region file for chunk errors.
+    pub fn path(&self) -> &PathBuf {
+        match self {
+            McaError::Io { path, .. }
+            | McaError::TruncatedHeader { path }
+            | McaError::ChunkOutOfRange { path, .. }
+            | McaError::SectorOutOfBounds { path, .. }
+            | McaError::BadCompression { path, .. }
+            | McaError::Decompress { path, .. }
+            | McaError::ExternalChunk { path, .. }
+            | McaError::UnknownRegionCoordinates { path, .. }
+            | McaError::Nbt { path, .. }
+            | McaError::InvalidFile { path, .. }
+            | McaError::InvalidJson { path, .. }
+            | McaError::LegacyChunk { path, .. } => path,
+        }
+    }
+
+    /// The chunk the error occurred in, if it is specific to a single chunk.
+    pub fn chunk(&self) -> Option<ChunkPos> {
+        match self {
+            McaError::SectorOutOfBounds { chunk, .. }
+            | McaError::BadCompression { chunk, .. }
+            | McaError::Decompress { chunk, .. }
+            | McaError::ExternalChunk { chunk, .. }
+            | McaError::UnknownRegionCoordinates { chunk, .. }
+            | McaError::Nbt { chunk, .. }
+            | McaError::LegacyChunk { chunk, .. } => Some(*chunk),
+            McaError::Io { .. }
+            | McaError::TruncatedHeader { .. }
+            | McaError::ChunkOutOfRange { .. }
+            | McaError::InvalidFile { .. }
+            | McaError::InvalidJson { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for McaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            McaError::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            McaError::TruncatedHeader { path } => write!(
+                f,
+                "{}: file is too small to contain a region header",
+                path.display()
+            ),
+            McaError::ChunkOutOfRange {
+                path,
+                local_x,
+                local_z,
+            } => write!(
+                f,
+                "{}: chunk {}, {} is outside of the region",
+                path.display(),
+                local_x,
+                local_z
+            ),
+            McaError::SectorOutOfBounds { path, chunk } => write!(
+                f,
+                "{}: chunk {} points outside of the region file",
+                path.display(),
+                chunk
+            ),
+            McaError::BadCompression {
+                path,
+                chunk,
+                source,
+            } => write!(f, "{}: chunk {}: {}", path.display(), chunk, source),
+            McaError::Decompress {
+                path,
+                chunk,
+                source,
+            } => write!(
+                f,
+                "{}: chunk {} failed to decompress: {}",
+                path.display(),
+                chunk,
+                source
+            ),
+            McaError::ExternalChunk {
+                path,
+                chunk,
+                source,
+            } => write!(
+                f,
+                "{}: external file of chunk {}: {}",
+                path.display(),
+                chunk,
+                source
+            ),
+            McaError::UnknownRegionCoordinates { path, chunk } => write!(
+                f,
+                "{}: chunk {} is stored externally but the region coordinates are unknown",
+                path.display(),
+                chunk
+            ),
+            McaError::Nbt {
+                path,
+                chunk,
+                source,
+            } => write!(f, "{}: chunk {}: {}", path.display(), chunk, source),
+            McaError::InvalidFile { path, source } => {
+                write!(f, "{}: {}", path.display(), source)
+            }
+            McaError::InvalidJson { path, source } => {
+                write!(f, "{}: {}", path.display(), source)
+            }
+            McaError::LegacyChunk { path, chunk } => write!(
+                f,
+                "{}: chunk {} was converted from an older layout and can't be written",
+                path.display(),
+                chunk
+            ),
+        }
+    }
+}
+
+impl std::error::Error for McaError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            McaError::Io { source, .. }
+            | McaError::Decompress { source, .. }
+            | McaError::ExternalChunk { source, .. } => Some(source),
+            McaError::BadCompression { source, .. } => Some(source),
+            McaError::Nbt { source, .. } | McaError::InvalidFile { source, .. } => Some(source),
+            McaError::InvalidJson { source, .. } => Some(source),
+            McaError::TruncatedHeader { .. }
+            | McaError::ChunkOutOfRange { .. }
+            | McaError::SectorOutOfBounds { .. }
+            | McaError::UnknownRegionCoordinates { .. }
+            | McaError::LegacyChunk { .. } => None,
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use crate::{blocks, packed, region::BlockStatePalette};
+
+// Heightmaps have one entry per column of a chunk.
+pub(crate) const COLUMNS: usize = 256;
+
+/// The heightmaps stored in a chunk's `Heightmaps` compound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HeightmapKind {
+    // Highest block that blocks motion or holds a fluid, used for rain and mob spawning.
+    MotionBlocking,
+    // Same as `MotionBlocking` but ignores leaves.
+    MotionBlockingNoLeaves,
+    // Highest block that blocks motion.
+    OceanFloor,
+    // `OceanFloor` as it was during world generation.
+    OceanFloorWg,
+    // Highest block that isn't air.
+    WorldSurface,
+    // `WorldSurface` as it was during world generation.
+    WorldSurfaceWg,
+}
+
+impl HeightmapKind {
+    pub const ALL: [HeightmapKind; 6] = [
+        HeightmapKind::MotionBlocking,
+        HeightmapKind::MotionBlockingNoLeaves,
+        HeightmapKind::OceanFloor,
+        HeightmapKind::OceanFloorWg,
+        HeightmapKind::WorldSurface,
+        HeightmapKind::WorldSurfaceWg,
+    ];
+
+    /// Name of the heightmap in the chunk NBT, e.g. `MOTION_BLOCKING`.
+    pub fn nbt_name(&self) -> &'static str {
+        match self {
+            HeightmapKind::MotionBlocking => "MOTION_BLOCKING",
+            HeightmapKind::MotionBlockingNoLeaves => "MOTION_BLOCKING_NO_LEAVES",
+            HeightmapKind::OceanFloor => "OCEAN_FLOOR",
+            HeightmapKind::OceanFloorWg => "OCEAN_FLOOR_WG",
+            HeightmapKind::WorldSurface => "WORLD_SURFACE",
+            HeightmapKind::WorldSurfaceWg => "WORLD_SURFACE_WG",
+        }
+    }
+
+    /// Whether `state` counts towards the height of a column for this heightmap.
+    pub fn matches(&self, state: &BlockStatePalette) -> bool {
+        match self {
+            HeightmapKind::MotionBlocking => {
+                blocks::blocks_motion(state) || blocks::has_fluid(state)
+            }
+            HeightmapKind::MotionBlockingNoLeaves => {
+                (blocks::blocks_motion(state) || blocks::has_fluid(state))
+                    && !blocks::is_leaves(state)
+            }
+            HeightmapKind::OceanFloor | HeightmapKind::OceanFloorWg => blocks::blocks_motion(state),
+            HeightmapKind::WorldSurface | HeightmapKind::WorldSurfaceWg => !blocks::is_air(state),
+        }
+    }
+}
+
+/// Decodes a heightmap of a world `height` blocks tall starting at `min_y` into the world Y just
+/// above the highest matching block of every column, ordered by `z * 16 + x`. Columns without a
+/// matching block are at `min_y`. `None` if the data doesn't have the length of a packed
+/// heightmap for that height.
+pub(crate) fn decode(data: &[i64], min_y: i32, height: i32) -> Option<[i32; COLUMNS]> {
+    let mut heights = [0; COLUMNS];
+    if data.is_empty() {
+        heights.fill(min_y);
+        return Some(heights);
+    }
+    // Values go from 0 (empty column) to `height` inclusive, as in `encode`.
+    let bits = packed::bits_per_entry(height as usize + 1, 1);
+    if data.len() != packed::packed_len(COLUMNS, bits) {
+        return None;
+    }
+    let mut values = [0u16; COLUMNS];
+    packed::unpack(data, bits, &mut values);
+    for (height, value) in heights.iter_mut().zip(values) {
+        *height = min_y + value as i32;
+    }
+    Some(heights)
+}
+
+/// Encodes world Y heights for a world `height` blocks tall starting at `min_y`, heights are
+/// clamped to the world.
+pub(crate) fn encode(heights: &[i32; COLUMNS], min_y: i32, height: i32) -> Vec<i64> {
+    // Values go from 0 (empty column) to `height` inclusive.
+    let bits = packed::bits_per_entry(height as usize + 1, 1);
+    let values: Vec<u16> = heights
+        .iter()
+        .map(|y| (y - min_y).clamp(0, height) as u16)
+        .collect();
+    packed::pack(&values, bits)
+}
+
+#[test]
+fn test_heightmap_round_trip() {
+    let mut heights = [-64; COLUMNS];
+    heights[0] = 320;
+    heights[17] = 63;
+    heights[255] = -10;
+    let data = encode(&heights, -64, 384);
+    // 9 bits per column, 7 columns per long.
+    assert_eq!(data.len(), 37);
+    assert_eq!(decode(&data, -64, 384), Some(heights));
+    assert_eq!(decode(&data[..36], -64, 384), None);
+    assert_eq!(decode(&[0; 256], -64, 384), None);
+
+    // A dimension type from a data pack, 128 blocks tall starting at Y 32, takes 8 bits.
+    let mut heights = [32; COLUMNS];
+    heights[1] = 160;
+    heights[200] = 100;
+    let data = encode(&heights, 32, 128);
+    assert_eq!(data.len(), 32);
+    assert_eq!(decode(&data, 32, 128), Some(heights));
+    // The same data doesn't decode as a heightmap of a taller world.
+    assert_eq!(decode(&data, 32, 384), None);
+}
@@ -0,0 +1,347 @@
+// Chunks saved before 1.18 nest everything in a `Level` compound. From 1.13 sections store their
+// palette as `Palette` and `BlockStates`, before that blocks are numeric ids in `Blocks`, `Add`
+// and `Data`, and McRegion chunks (before 1.2) don't have sections at all. Biomes are numeric ids
+// for the whole chunk. They are all converted into the 1.18 layout when read, see `RegionChunk`.
+
+use std::{collections::HashMap, fmt};
+
+use serde::{
+    de::{self, Visitor},
+    Deserialize, Deserializer,
+};
+
+use crate::{
+    entities::BlockEntity,
+    heightmap::COLUMNS,
+    legacy_ids::{legacy_block_state, LegacyPalette},
+    light::NibbleArray,
+    packed,
+    region::{BlockStatePalette, Structures, TileTicks},
+};
+
+/// First data version (21w43a) that stores chunks without the `Level` compound.
+pub(crate) const FLAT_CHUNK_VERSION: i32 = 2844;
+/// First data version (17w47a) that stores blocks by name. Chunks from before 1.9 have no data
+/// version at all, which reads as 0.
+pub(crate) const FLATTENING_VERSION: i32 = 1451;
+// First data version (20w17a) in which packed entries no longer span two longs.
+const NON_SPANNING_VERSION: i32 = 2529;
+// Biomes are stored per 4x4x4 cell since 19w36a, before that per column.
+const BIOME_CELLS_VERSION: i32 = 2203;
+
+/// Height of every dimension before 1.18, from Y 0 to 256.
+pub(crate) const WORLD_HEIGHT: i32 = 256;
+// Heightmaps before 1.18 cover a world from Y 0 to 256, which takes 9 bits per column.
+const HEIGHTMAP_BITS: u32 = 9;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub(crate) struct Level {
+    #[serde(rename = "xPos")]
+    pub(crate) x_pos: i32,
+    #[serde(rename = "zPos")]
+    pub(crate) z_pos: i32,
+    #[serde(default)]
+    pub(crate) last_update: i64,
+    #[serde(default)]
+    pub(crate) inhabited_time: i64,
+    pub(crate) status: Option<String>,
+    #[serde(default)]
+    pub(crate) sections: Vec<LegacySection>,
+    pub(crate) biomes: Option<Vec<i32>>,
+    pub(crate) tile_entities: Option<Vec<BlockEntity>>,
+    // Keyed by heightmap name, e.g. `MOTION_BLOCKING`.
+    #[serde(default)]
+    pub(crate) heightmaps: HashMap<String, Vec<i64>>,
+    #[serde(rename = "isLightOn")]
+    pub(crate) is_light_on: Option<bool>,
+    #[serde(default)]
+    pub(crate) tile_ticks: Vec<TileTicks>,
+    #[serde(default)]
+    pub(crate) liquid_ticks: Vec<TileTicks>,
+    #[serde(default)]
+    pub(crate) post_processing: Vec<Vec<i16>>,
+    pub(crate) structures: Option<Structures>,
+    // McRegion chunks store the blocks and light of the whole 128 block tall chunk at once,
+    // ordered by `x * 2048 + z * 128 + y`.
+    pub(crate) blocks: Option<Vec<i8>>,
+    pub(crate) data: Option<NibbleArray>,
+    pub(crate) block_light: Option<NibbleArray>,
+    pub(crate) sky_light: Option<NibbleArray>,
+}
+
+impl Level {
+    /// Takes the sections of the chunk, McRegion chunks are split into sections first.
+    pub(crate) fn take_sections(&mut self) -> Vec<LegacySection> {
+        match self.blocks.take() {
+            Some(blocks) => (0..8)
+                .map(|section_y| self.mcregion_section(&blocks, section_y))
+                .collect(),
+            None => std::mem::take(&mut self.sections),
+        }
+    }
+
+    fn mcregion_section(&self, blocks: &[i8], section_y: usize) -> LegacySection {
+        let mut section_blocks = vec![0; 4096];
+        let mut data = NibbleArray::new(0);
+        let mut block_light = NibbleArray::new(0);
+        let mut sky_light = NibbleArray::new(0);
+        for (i, block) in section_blocks.iter_mut().enumerate() {
+            let (x, y, z) = (i % 16, i / 256, (i / 16) % 16);
+            let old_index = x * 2048 + z * 128 + section_y * 16 + y;
+            *block = blocks.get(old_index).copied().unwrap_or(0);
+            let nibble = |array: &Option<NibbleArray>| {
+                array.as_ref().map_or(0, |array| array.get(old_index))
+            };
+            data.set(i, nibble(&self.data));
+            block_light.set(i, nibble(&self.block_light));
+            sky_light.set(i, nibble(&self.sky_light));
+        }
+        LegacySection {
+            y: section_y as i8,
+            palette: None,
+            block_states: None,
+            blocks: Some(section_blocks),
+            add: None,
+            data: Some(data),
+            block_light: self.block_light.as_ref().map(|_| block_light),
+            sky_light: self.sky_light.as_ref().map(|_| sky_light),
+        }
+    }
+}
+
+// A section's block palette and the palette index of every block.
+type SectionBlocks = (Vec<BlockStatePalette>, Vec<u16>);
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub(crate) struct LegacySection {
+    #[serde(rename = "Y")]
+    pub(crate) y: i8,
+    // Missing in sections that only hold light.
+    pub(crate) palette: Option<Vec<BlockStatePalette>>,
+    pub(crate) block_states: Option<Vec<i64>>,
+    // Numeric ids before 1.13, `Add` holds the upper 4 bits of ids above 255.
+    pub(crate) blocks: Option<Vec<i8>>,
+    pub(crate) add: Option<NibbleArray>,
+    pub(crate) data: Option<NibbleArray>,
+    pub(crate) block_light: Option<NibbleArray>,
+    pub(crate) sky_light: Option<NibbleArray>,
+}
+
+impl LegacySection {
+    /// Takes the block palette and the palette index of every block, ordered by
+    /// `y * 256 + z * 16 + x` like in newer sections. `None` if the section only holds light.
+    pub(crate) fn take_blocks(
+        &mut self,
+        data_version: i32,
+    ) -> Result<Option<SectionBlocks>, String> {
+        if self.blocks.is_some() {
+            return Ok(Some(self.decode_numeric()));
+        }
+        if self.palette.is_none() {
+            return Ok(None);
+        }
+        let indices = self.decode(data_version)?;
+        Ok(self.palette.take().map(|palette| (palette, indices)))
+    }
+
+    fn decode_numeric(&self) -> SectionBlocks {
+        let blocks = self.blocks.as_deref().unwrap_or_default();
+        let mut palette = LegacyPalette::default();
+        let indices = (0..4096)
+            .map(|i| {
+                let low = blocks.get(i).map_or(0, |id| *id as u8 as u16);
+                let high = self.add.as_ref().map_or(0, |add| add.get(i) as u16);
+                let meta = self.data.as_ref().map_or(0, |data| data.get(i));
+                palette.index_of(high << 8 | low, meta)
+            })
+            .collect();
+        (palette.into_palette(), indices)
+    }
+
+    fn decode(&self, data_version: i32) -> Result<Vec<u16>, String> {
+        let mut indices = vec![0; 4096];
+        let data = self.block_states.as_deref().unwrap_or_default();
+        if data_version < NON_SPANNING_VERSION {
+            // Spanning entries fill the longs exactly, so the width follows from the length.
+            let bits = (data.len() * 64 / indices.len()) as u32;
+            if !(4..=16).contains(&bits) || data.len() != indices.len() * bits as usize / 64 {
+                return Err(format!(
+                    "section {} has {} longs of block states, which isn't a valid width",
+                    self.y,
+                    data.len()
+                ));
+            }
+            packed::unpack_spanning(data, bits, &mut indices);
+        } else {
+            let palette_len = self.palette.as_ref().map_or(0, Vec::len);
+            packed::unpack(data, packed::bits_per_entry(palette_len, 4), &mut indices);
+        }
+        Ok(indices)
+    }
+}
+
+/// Biome names of the 64 cells of section `section_y`, ordered by `y * 16 + z * 4 + x`.
+/// Chunks without biomes are all plains.
+pub(crate) fn section_biomes(
+    biomes: Option<&[i32]>,
+    section_y: i8,
+    data_version: i32,
+) -> Vec<&'static str> {
+    let biomes = biomes.unwrap_or_default();
+    (0..64)
+        .map(|cell| {
+            let (x, y, z) = (cell % 4, cell / 16, (cell / 4) % 4);
+            let id = if data_version < BIOME_CELLS_VERSION {
+                // One biome per column, use the column in the middle of the cell. Before 1.13
+                // these are bytes, so ids above 127 read as negative.
+                biomes.get((z * 4 + 2) * 16 + x * 4 + 2).map(|id| id & 0xFF)
+            } else {
+                // Cells above and below the stored range use the nearest stored cell.
+                let layers = (biomes.len() / 16).max(1) as i32;
+                let cell_y = (section_y as i32 * 4 + y as i32).clamp(0, layers - 1);
+                biomes.get(cell_y as usize * 16 + z * 4 + x).copied()
+            };
+            id.map_or("minecraft:plains", biome_name)
+        })
+        .collect()
+}
+
+/// Converts a heightmap to the layout used since 1.16 where entries don't span longs.
+pub(crate) fn heightmap(data: &[i64], data_version: i32) -> Vec<i64> {
+    if data_version >= NON_SPANNING_VERSION {
+        return data.to_vec();
+    }
+    let mut heights = [0; COLUMNS];
+    packed::unpack_spanning(data, HEIGHTMAP_BITS, &mut heights);
+    packed::pack(&heights, HEIGHTMAP_BITS)
+}
+
+/// Reads the block of a scheduled tick, which is a numeric id before 1.8.
+pub(crate) fn deserialize_tick_id<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct TickIdVisitor;
+
+    impl<'de> Visitor<'de> for TickIdVisitor {
+        type Value = String;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            write!(formatter, "a block name or numeric block id")
+        }
+
+        fn visit_str<E: de::Error>(self, id: &str) -> Result<Self::Value, E> {
+            Ok(id.to_string())
+        }
+
+        fn visit_i64<E: de::Error>(self, id: i64) -> Result<Self::Value, E> {
+            Ok(u16::try_from(id)
+                .ok()
+                .and_then(|id| legacy_block_state(id, 0))
+                .map_or_else(|| id.to_string(), |state| state.name().to_string()))
+        }
+    }
+
+    deserializer.deserialize_any(TickIdVisitor)
+}
+
+/// Name of a numeric biome id as used before 1.18. Biomes that 1.18 removed or renamed are
+/// mapped the same way the game upgrades them, unknown ids become plains.
+pub(crate) fn biome_name(id: i32) -> &'static str {
+    match id {
+        0 => "minecraft:ocean",
+        1 => "minecraft:plains",
+        2 | 17 | 130 => "minecraft:desert",
+        3 | 20 => "minecraft:windswept_hills",
+        4 | 18 => "minecraft:forest",
+        5 | 19 | 133 => "minecraft:taiga",
+        6 | 134 => "minecraft:swamp",
+        7 => "minecraft:river",
+        8 => "minecraft:nether_wastes",
+        9 => "minecraft:the_end",
+        10 => "minecraft:frozen_ocean",
+        11 => "minecraft:frozen_river",
+        12 | 13 => "minecraft:snowy_plains",
+        14 | 15 => "minecraft:mushroom_fields",
+        16 => "minecraft:beach",
+        21 | 22 | 149 => "minecraft:jungle",
+        23 | 151 => "minecraft:sparse_jungle",
+        24 => "minecraft:deep_ocean",
+        25 => "minecraft:stony_shore",
+        26 => "minecraft:snowy_beach",
+        27 | 28 => "minecraft:birch_forest",
+        29 | 157 => "minecraft:dark_forest",
+        30 | 31 | 158 => "minecraft:snowy_taiga",
+        32 | 33 => "minecraft:old_growth_pine_taiga",
+        34 => "minecraft:windswept_forest",
+        35 => "minecraft:savanna",
+        36 => "minecraft:savanna_plateau",
+        37 | 39 | 167 => "minecraft:badlands",
+        38 | 166 => "minecraft:wooded_badlands",
+        40 => "minecraft:small_end_islands",
+        41 => "minecraft:end_midlands",
+        42 => "minecraft:end_highlands",
+        43 => "minecraft:end_barrens",
+        44 | 47 => "minecraft:warm_ocean",
+        45 => "minecraft:lukewarm_ocean",
+        46 => "minecraft:cold_ocean",
+        48 => "minecraft:deep_lukewarm_ocean",
+        49 => "minecraft:deep_cold_ocean",
+        50 => "minecraft:deep_frozen_ocean",
+        127 => "minecraft:the_void",
+        129 => "minecraft:sunflower_plains",
+        131 | 162 => "minecraft:windswept_gravelly_hills",
+        132 => "minecraft:flower_forest",
+        140 => "minecraft:ice_spikes",
+        155 | 156 => "minecraft:old_growth_birch_forest",
+        160 | 161 => "minecraft:old_growth_spruce_taiga",
+        163 | 164 => "minecraft:windswept_savanna",
+        165 => "minecraft:eroded_badlands",
+        168 | 169 => "minecraft:bamboo_jungle",
+        170 => "minecraft:soul_sand_valley",
+        171 => "minecraft:crimson_forest",
+        172 => "minecraft:warped_forest",
+        173 => "minecraft:basalt_deltas",
+        174 => "minecraft:dripstone_caves",
+        175 => "minecraft:lush_caves",
+        _ => "minecraft:plains",
+    }
+}
+
+#[test]
+fn test_section_biomes() {
+    // Column biomes: the east half of the chunk is desert.
+    let columns: Vec<i32> = (0..256).map(|i| if i % 16 >= 8 { 2 } else { 1 }).collect();
+    let biomes = section_biomes(Some(&columns), 3, 1976);
+    assert_eq!(biomes[0], "minecraft:plains");
+    assert_eq!(biomes[63], "minecraft:desert");
+
+    // Cell biomes, 16 cells per layer and 4 layers per section.
+    let mut cells = vec![1; 1024];
+    cells[4 * 16] = 13;
+    let biomes = section_biomes(Some(&cells), 1, 2586);
+    assert_eq!(biomes[0], "minecraft:snowy_plains");
+    assert_eq!(biomes[1], "minecraft:plains");
+    assert_eq!(section_biomes(None, 0, 2586)[0], "minecraft:plains");
+}
+
+#[test]
+fn test_decode_invalid_width() {
+    let section = |longs: usize| LegacySection {
+        y: 0,
+        palette: Some(vec![BlockStatePalette::new("minecraft:stone")]),
+        block_states: Some(vec![0; longs]),
+        blocks: None,
+        add: None,
+        data: None,
+        block_light: None,
+        sky_light: None,
+    };
+    assert_eq!(section(256).decode(1631), Ok(vec![0; 4096]));
+    // 64 bits per block would overflow the mask and 350 longs aren't a whole number of bits.
+    assert!(section(4096).decode(1631).is_err());
+    assert!(section(350).decode(1631).is_err());
+    assert!(section(0).decode(1631).is_err());
+}
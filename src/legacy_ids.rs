@@ -0,0 +1,488 @@
+// Before 1.13 blocks were stored as a numeric id and 4 bits of metadata. This maps them to the
+// block states the game upgrades them to, keeping the properties that analysis cares about such
+// as slab halves and log axes. Properties that depend on neighbouring blocks or block entities,
+// e.g. fence connections or bed colours, are left to their defaults.
+
+use std::collections::HashMap;
+
+use crate::region::BlockStatePalette;
+
+const COLORS: [&str; 16] = [
+    "white",
+    "orange",
+    "magenta",
+    "light_blue",
+    "yellow",
+    "lime",
+    "pink",
+    "gray",
+    "light_gray",
+    "cyan",
+    "purple",
+    "blue",
+    "brown",
+    "green",
+    "red",
+    "black",
+];
+
+const WOODS: [&str; 6] = ["oak", "spruce", "birch", "jungle", "acacia", "dark_oak"];
+
+// Slab materials of `stone_slab` (44) and `double_stone_slab` (43).
+const STONE_SLABS: [&str; 8] = [
+    "smooth_stone",
+    "sandstone",
+    "petrified_oak",
+    "cobblestone",
+    "brick",
+    "stone_brick",
+    "nether_brick",
+    "quartz",
+];
+
+/// The block state a block stored as numeric `id` and `meta` before 1.13 (the flattening) is
+/// upgraded to, e.g. 35:14 is `minecraft:red_wool`. `None` for ids vanilla never used, such as
+/// blocks added by mods.
+///
+/// The upper half of a double plant doesn't store which plant it is, it's read as the upper half
+/// of a sunflower.
+pub fn legacy_block_state(id: u16, meta: u8) -> Option<BlockStatePalette> {
+    let meta = meta & 0xF;
+    let level = meta.to_string();
+    let color = COLORS[meta as usize];
+    let half = if meta & 8 != 0 { "upper" } else { "lower" };
+    let slab_type = if meta & 8 != 0 { "top" } else { "bottom" };
+    let lit = |lit: bool| if lit { "true" } else { "false" };
+
+    let state = match id {
+        0 => block("air"),
+        1 => block(match meta {
+            1 => "granite",
+            2 => "polished_granite",
+            3 => "diorite",
+            4 => "polished_diorite",
+            5 => "andesite",
+            6 => "polished_andesite",
+            _ => "stone",
+        }),
+        2 => block("grass_block"),
+        3 => block(match meta {
+            1 => "coarse_dirt",
+            2 => "podzol",
+            _ => "dirt",
+        }),
+        4 => block("cobblestone"),
+        5 => block(&format!("{}_planks", wood(meta))),
+        6 => block(&format!("{}_sapling", wood(meta & 7))),
+        7 => block("bedrock"),
+        8 | 9 => with("water", &[("level", &level)]),
+        10 | 11 => with("lava", &[("level", &level)]),
+        12 => block(if meta == 1 { "red_sand" } else { "sand" }),
+        13 => block("gravel"),
+        14 => block("gold_ore"),
+        15 => block("iron_ore"),
+        16 => block("coal_ore"),
+        17 => log(WOODS[(meta & 3) as usize], meta),
+        18 => block(&format!("{}_leaves", WOODS[(meta & 3) as usize])),
+        19 => block(if meta == 1 { "wet_sponge" } else { "sponge" }),
+        20 => block("glass"),
+        21 => block("lapis_ore"),
+        22 => block("lapis_block"),
+        23 => block("dispenser"),
+        24 => block(sandstone("sandstone", meta)),
+        25 => block("note_block"),
+        26 => with(
+            "red_bed",
+            &[("part", if meta & 8 != 0 { "head" } else { "foot" })],
+        ),
+        27 => block("powered_rail"),
+        28 => block("detector_rail"),
+        29 => block("sticky_piston"),
+        30 => block("cobweb"),
+        31 => block(match meta {
+            0 => "dead_bush",
+            2 => "fern",
+            _ => "short_grass",
+        }),
+        32 => block("dead_bush"),
+        33 => block("piston"),
+        34 => block("piston_head"),
+        35 => block(&format!("{}_wool", color)),
+        36 => block("moving_piston"),
+        37 => block("dandelion"),
+        38 => block(match meta {
+            1 => "blue_orchid",
+            2 => "allium",
+            3 => "azure_bluet",
+            4 => "red_tulip",
+            5 => "orange_tulip",
+            6 => "white_tulip",
+            7 => "pink_tulip",
+            8 => "oxeye_daisy",
+            _ => "poppy",
+        }),
+        39 => block("brown_mushroom"),
+        40 => block("red_mushroom"),
+        41 => block("gold_block"),
+        42 => block("iron_block"),
+        43 => slab(STONE_SLABS[(meta & 7) as usize], "double"),
+        44 => slab(STONE_SLABS[(meta & 7) as usize], slab_type),
+        45 => block("bricks"),
+        46 => block("tnt"),
+        47 => block("bookshelf"),
+        48 => block("mossy_cobblestone"),
+        49 => block("obsidian"),
+        50 => torch("torch", "wall_torch", meta, None),
+        51 => block("fire"),
+        52 => block("spawner"),
+        53 => stairs("oak", meta),
+        54 => block("chest"),
+        55 => with("redstone_wire", &[("power", &level)]),
+        56 => block("diamond_ore"),
+        57 => block("diamond_block"),
+        58 => block("crafting_table"),
+        59 => with("wheat", &[("age", &(meta & 7).to_string())]),
+        60 => block("farmland"),
+        61 | 62 => with("furnace", &[("lit", lit(id == 62))]),
+        63 => block("oak_sign"),
+        64 => with("oak_door", &[("half", half)]),
+        65 => block("ladder"),
+        66 => block("rail"),
+        67 => stairs("cobblestone", meta),
+        68 => block("oak_wall_sign"),
+        69 => block("lever"),
+        70 => block("stone_pressure_plate"),
+        71 => with("iron_door", &[("half", half)]),
+        72 => block("oak_pressure_plate"),
+        73 | 74 => with("redstone_ore", &[("lit", lit(id == 74))]),
+        75 | 76 => torch(
+            "redstone_torch",
+            "redstone_wall_torch",
+            meta,
+            Some(lit(id == 76)),
+        ),
+        77 => block("stone_button"),
+        78 => with("snow", &[("layers", &((meta & 7) + 1).to_string())]),
+        79 => block("ice"),
+        80 => block("snow_block"),
+        81 => block("cactus"),
+        82 => block("clay"),
+        83 => block("sugar_cane"),
+        84 => block("jukebox"),
+        85 => block("oak_fence"),
+        86 => block("carved_pumpkin"),
+        87 => block("netherrack"),
+        88 => block("soul_sand"),
+        89 => block("glowstone"),
+        90 => block("nether_portal"),
+        91 => block("jack_o_lantern"),
+        92 => block("cake"),
+        93 | 94 => with("repeater", &[("powered", lit(id == 94))]),
+        95 => block(&format!("{}_stained_glass", color)),
+        96 => block("oak_trapdoor"),
+        97 => block(match meta {
+            1 => "infested_cobblestone",
+            2 => "infested_stone_bricks",
+            3 => "infested_mossy_stone_bricks",
+            4 => "infested_cracked_stone_bricks",
+            5 => "infested_chiseled_stone_bricks",
+            _ => "infested_stone",
+        }),
+        98 => block(match meta {
+            1 => "mossy_stone_bricks",
+            2 => "cracked_stone_bricks",
+            3 => "chiseled_stone_bricks",
+            _ => "stone_bricks",
+        }),
+        99 | 100 if meta == 10 || meta == 15 => block("mushroom_stem"),
+        99 => block("brown_mushroom_block"),
+        100 => block("red_mushroom_block"),
+        101 => block("iron_bars"),
+        102 => block("glass_pane"),
+        103 => block("melon"),
+        104 => with("pumpkin_stem", &[("age", &(meta & 7).to_string())]),
+        105 => with("melon_stem", &[("age", &(meta & 7).to_string())]),
+        106 => block("vine"),
+        107 => block("oak_fence_gate"),
+        108 => stairs("brick", meta),
+        109 => stairs("stone_brick", meta),
+        110 => block("mycelium"),
+        111 => block("lily_pad"),
+        112 => block("nether_bricks"),
+        113 => block("nether_brick_fence"),
+        114 => stairs("nether_brick", meta),
+        115 => with("nether_wart", &[("age", &(meta & 3).to_string())]),
+        116 => block("enchanting_table"),
+        117 => block("brewing_stand"),
+        118 => block("cauldron"),
+        119 => block("end_portal"),
+        120 => block("end_portal_frame"),
+        121 => block("end_stone"),
+        122 => block("dragon_egg"),
+        123 | 124 => with("redstone_lamp", &[("lit", lit(id == 124))]),
+        125 => slab(wood(meta & 7), "double"),
+        126 => slab(wood(meta & 7), slab_type),
+        127 => block("cocoa"),
+        128 => stairs("sandstone", meta),
+        129 => block("emerald_ore"),
+        130 => block("ender_chest"),
+        131 => block("tripwire_hook"),
+        132 => block("tripwire"),
+        133 => block("emerald_block"),
+        134 => stairs("spruce", meta),
+        135 => stairs("birch", meta),
+        136 => stairs("jungle", meta),
+        137 => block("command_block"),
+        138 => block("beacon"),
+        139 => block(if meta == 1 {
+            "mossy_cobblestone_wall"
+        } else {
+            "cobblestone_wall"
+        }),
+        140 => block("flower_pot"),
+        141 => with("carrots", &[("age", &(meta & 7).to_string())]),
+        142 => with("potatoes", &[("age", &(meta & 7).to_string())]),
+        143 => block("oak_button"),
+        144 => block("skeleton_skull"),
+        145 => block(match meta >> 2 {
+            1 => "chipped_anvil",
+            2 => "damaged_anvil",
+            _ => "anvil",
+        }),
+        146 => block("trapped_chest"),
+        147 => block("light_weighted_pressure_plate"),
+        148 => block("heavy_weighted_pressure_plate"),
+        149 | 150 => with("comparator", &[("powered", lit(id == 150))]),
+        151 => with("daylight_detector", &[("inverted", "false")]),
+        152 => block("redstone_block"),
+        153 => block("nether_quartz_ore"),
+        154 => block("hopper"),
+        155 => match meta {
+            1 => block("chiseled_quartz_block"),
+            2 => with("quartz_pillar", &[("axis", "y")]),
+            3 => with("quartz_pillar", &[("axis", "x")]),
+            4 => with("quartz_pillar", &[("axis", "z")]),
+            _ => block("quartz_block"),
+        },
+        156 => stairs("quartz", meta),
+        157 => block("activator_rail"),
+        158 => block("dropper"),
+        159 => block(&format!("{}_terracotta", color)),
+        160 => block(&format!("{}_stained_glass_pane", color)),
+        161 => block(&format!("{}_leaves", WOODS[4 + (meta & 1) as usize])),
+        162 => log(WOODS[4 + (meta & 1) as usize], meta),
+        163 => stairs("acacia", meta),
+        164 => stairs("dark_oak", meta),
+        165 => block("slime_block"),
+        166 => block("barrier"),
+        167 => block("iron_trapdoor"),
+        168 => block(match meta {
+            1 => "prismarine_bricks",
+            2 => "dark_prismarine",
+            _ => "prismarine",
+        }),
+        169 => block("sea_lantern"),
+        170 => with("hay_block", &[("axis", axis(meta))]),
+        171 => block(&format!("{}_carpet", color)),
+        172 => block("terracotta"),
+        173 => block("coal_block"),
+        174 => block("packed_ice"),
+        175 if meta & 8 != 0 => with("sunflower", &[("half", "upper")]),
+        175 => with(
+            match meta {
+                1 => "lilac",
+                2 => "tall_grass",
+                3 => "large_fern",
+                4 => "rose_bush",
+                5 => "peony",
+                _ => "sunflower",
+            },
+            &[("half", "lower")],
+        ),
+        176 => block("white_banner"),
+        177 => block("white_wall_banner"),
+        178 => with("daylight_detector", &[("inverted", "true")]),
+        179 => block(sandstone("red_sandstone", meta)),
+        180 => stairs("red_sandstone", meta),
+        181 => slab("red_sandstone", "double"),
+        182 => slab("red_sandstone", slab_type),
+        183..=187 => block(&format!(
+            "{}_fence_gate",
+            ["spruce", "birch", "jungle", "dark_oak", "acacia"][(id - 183) as usize]
+        )),
+        188..=192 => block(&format!(
+            "{}_fence",
+            ["spruce", "birch", "jungle", "dark_oak", "acacia"][(id - 188) as usize]
+        )),
+        193..=197 => with(
+            &format!(
+                "{}_door",
+                ["spruce", "birch", "jungle", "acacia", "dark_oak"][(id - 193) as usize]
+            ),
+            &[("half", half)],
+        ),
+        198 => block("end_rod"),
+        199 => block("chorus_plant"),
+        200 => block("chorus_flower"),
+        201 => block("purpur_block"),
+        202 => with("purpur_pillar", &[("axis", axis(meta))]),
+        203 => stairs("purpur", meta),
+        204 => slab("purpur", "double"),
+        205 => slab("purpur", slab_type),
+        206 => block("end_stone_bricks"),
+        207 => with("beetroots", &[("age", &(meta & 3).to_string())]),
+        208 => block("dirt_path"),
+        209 => block("end_gateway"),
+        210 => block("repeating_command_block"),
+        211 => block("chain_command_block"),
+        212 => block("frosted_ice"),
+        213 => block("magma_block"),
+        214 => block("nether_wart_block"),
+        215 => block("red_nether_bricks"),
+        216 => with("bone_block", &[("axis", axis(meta))]),
+        217 => block("structure_void"),
+        218 => block("observer"),
+        219..=234 => block(&format!("{}_shulker_box", COLORS[(id - 219) as usize])),
+        235..=250 => block(&format!(
+            "{}_glazed_terracotta",
+            COLORS[(id - 235) as usize]
+        )),
+        251 => block(&format!("{}_concrete", color)),
+        252 => block(&format!("{}_concrete_powder", color)),
+        255 => block("structure_block"),
+        _ => return None,
+    };
+    Some(state)
+}
+
+/// Converts numeric blocks to palette indices, looking every id and metadata pair up once.
+/// Unknown ids become air, like the game does when it upgrades a world.
+#[derive(Debug, Default)]
+pub(crate) struct LegacyPalette {
+    palette: Vec<BlockStatePalette>,
+    indices: HashMap<(u16, u8), u16>,
+}
+
+impl LegacyPalette {
+    pub(crate) fn index_of(&mut self, id: u16, meta: u8) -> u16 {
+        if let Some(index) = self.indices.get(&(id, meta)) {
+            return *index;
+        }
+        let state =
+            legacy_block_state(id, meta).unwrap_or_else(|| BlockStatePalette::new("minecraft:air"));
+        // Several ids can upgrade to the same state, e.g. flowing and still water.
+        let index = match self.palette.iter().position(|entry| *entry == state) {
+            Some(index) => index,
+            None => {
+                self.palette.push(state);
+                self.palette.len() - 1
+            }
+        } as u16;
+        self.indices.insert((id, meta), index);
+        index
+    }
+
+    pub(crate) fn into_palette(self) -> Vec<BlockStatePalette> {
+        self.palette
+    }
+}
+
+fn block(name: &str) -> BlockStatePalette {
+    BlockStatePalette::new(&format!("minecraft:{}", name))
+}
+
+fn with(name: &str, properties: &[(&str, &str)]) -> BlockStatePalette {
+    BlockStatePalette::with_properties(
+        &format!("minecraft:{}", name),
+        properties
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect(),
+    )
+}
+
+fn wood(meta: u8) -> &'static str {
+    WOODS.get(meta as usize).copied().unwrap_or("oak")
+}
+
+// The axis of logs, pillars and similar blocks is stored in the upper two bits.
+fn axis(meta: u8) -> &'static str {
+    match (meta >> 2) & 3 {
+        1 => "x",
+        2 => "z",
+        _ => "y",
+    }
+}
+
+// Logs with the axis bits both set have bark on every side.
+fn log(wood: &str, meta: u8) -> BlockStatePalette {
+    if meta >> 2 == 3 {
+        with(&format!("{}_wood", wood), &[("axis", "y")])
+    } else {
+        with(&format!("{}_log", wood), &[("axis", axis(meta))])
+    }
+}
+
+fn slab(material: &str, slab_type: &str) -> BlockStatePalette {
+    with(&format!("{}_slab", material), &[("type", slab_type)])
+}
+
+fn stairs(material: &str, meta: u8) -> BlockStatePalette {
+    let facing = ["east", "west", "south", "north"][(meta & 3) as usize];
+    let half = if meta & 4 != 0 { "top" } else { "bottom" };
+    with(
+        &format!("{}_stairs", material),
+        &[("facing", facing), ("half", half)],
+    )
+}
+
+fn sandstone(name: &'static str, meta: u8) -> &'static str {
+    match (name, meta) {
+        ("sandstone", 1) => "chiseled_sandstone",
+        ("sandstone", 2) => "cut_sandstone",
+        ("red_sandstone", 1) => "chiseled_red_sandstone",
+        ("red_sandstone", 2) => "cut_red_sandstone",
+        _ => name,
+    }
+}
+
+// Torches on a wall store the direction they face, standing torches are 5 (or 0).
+fn torch(name: &str, wall_name: &str, meta: u8, lit: Option<&str>) -> BlockStatePalette {
+    let facing = match meta {
+        1 => Some("east"),
+        2 => Some("west"),
+        3 => Some("south"),
+        4 => Some("north"),
+        _ => None,
+    };
+    let mut properties = Vec::new();
+    if let Some(lit) = lit {
+        properties.push(("lit", lit));
+    }
+    match facing {
+        Some(facing) => {
+            properties.push(("facing", facing));
+            with(wall_name, &properties)
+        }
+        None => with(name, &properties),
+    }
+}
+
+#[test]
+fn test_legacy_block_state() {
+    assert_eq!(
+        legacy_block_state(35, 14).unwrap().name(),
+        "minecraft:red_wool"
+    );
+    let slab = legacy_block_state(44, 8 | 3).unwrap();
+    assert_eq!(slab.name(), "minecraft:cobblestone_slab");
+    assert_eq!(slab.property("type"), Some("top"));
+    let log = legacy_block_state(162, 1 | 4).unwrap();
+    assert_eq!(log.to_string(), "minecraft:dark_oak_log[axis=x]");
+    assert!(legacy_block_state(4000, 0).is_none());
+
+    let mut palette = LegacyPalette::default();
+    assert_eq!(palette.index_of(9, 0), 0);
+    assert_eq!(palette.index_of(1, 0), 1);
+    assert_eq!(palette.index_of(9, 0), 0);
+}
@@ -0,0 +1,314 @@
+use std::{collections::HashMap, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dat::{read_gzip_nbt, write_gzip_nbt},
+    error::McaError,
+    nbt_serde,
+    uuid::Uuid,
+    vec::Vec3I,
+};
+
+// level.dat wraps everything in a `Data` compound.
+#[derive(Debug, Deserialize, Serialize)]
+struct LevelDat {
+    #[serde(rename = "Data")]
+    data: LevelData,
+}
+
+/// Contents of a world's `level.dat`.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct LevelData {
+    // Missing before 1.9.
+    data_version: Option<i32>,
+    level_name: String,
+    spawn_x: i32,
+    spawn_y: i32,
+    spawn_z: i32,
+    spawn_angle: Option<f32>,
+    // Moved into `WorldGenSettings` in 1.16, older worlds store the seed here.
+    random_seed: Option<i64>,
+    world_gen_settings: Option<WorldGenSettings>,
+    // Values are stored as strings, e.g. "true" or "3".
+    #[serde(default)]
+    game_rules: HashMap<String, String>,
+    data_packs: Option<DataPacks>,
+    version: Option<Version>,
+    // NBT format version of the level, 19133 for anvil worlds and 19132 for McRegion ones.
+    #[serde(rename = "version")]
+    nbt_version: Option<i32>,
+    time: i64,
+    day_time: i64,
+    last_played: i64,
+    game_type: i32,
+    difficulty: Option<i8>,
+    difficulty_locked: Option<bool>,
+    #[serde(rename = "hardcore")]
+    hardcore: bool,
+    #[serde(rename = "allowCommands")]
+    allow_commands: bool,
+    #[serde(rename = "initialized")]
+    initialized: bool,
+    #[serde(rename = "raining")]
+    raining: bool,
+    #[serde(rename = "rainTime")]
+    rain_time: i32,
+    #[serde(rename = "thundering")]
+    thundering: bool,
+    #[serde(rename = "thunderTime")]
+    thunder_time: i32,
+    // Added in 1.8.
+    #[serde(rename = "clearWeatherTime")]
+    clear_weather_time: Option<i32>,
+    wandering_trader_id: Option<Uuid>,
+    wandering_trader_spawn_chance: Option<i32>,
+    wandering_trader_spawn_delay: Option<i32>,
+    border_center_x: Option<f64>,
+    border_center_z: Option<f64>,
+    border_damage_per_block: Option<f64>,
+    border_safe_zone: Option<f64>,
+    border_size: Option<f64>,
+    border_size_lerp_target: Option<f64>,
+    border_size_lerp_time: Option<i64>,
+    border_warning_blocks: Option<f64>,
+    border_warning_time: Option<f64>,
+    #[serde(rename = "ServerBrands")]
+    server_brands: Option<Vec<String>>,
+    #[serde(rename = "WasModded")]
+    was_modded: Option<bool>,
+    #[serde(rename = "enabled_features")]
+    enabled_features: Option<Vec<String>>,
+    // The remaining compounds aren't modelled yet but are kept so writing the level back
+    // doesn't lose them.
+    #[serde(default, with = "nbt_serde::option")]
+    player: Option<nbt::Value>,
+    #[serde(default, with = "nbt_serde::option")]
+    dragon_fight: Option<nbt::Value>,
+    #[serde(default, with = "nbt_serde::option")]
+    custom_boss_events: Option<nbt::Value>,
+    #[serde(default, with = "nbt_serde::option")]
+    scheduled_events: Option<nbt::Value>,
+    // Anything else, e.g. keys of other versions or mods, so they survive a round trip too.
+    #[serde(rename = "$extra", default, with = "nbt_serde::compound")]
+    extra: HashMap<String, nbt::Value>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct WorldGenSettings {
+    seed: i64,
+    generate_features: bool,
+    bonus_chest: bool,
+    dimensions: HashMap<String, DimensionGenerator>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DimensionGenerator {
+    // Dimension type, e.g. minecraft:overworld.
+    #[serde(rename = "type")]
+    typ: String,
+    // Generator settings are arbitrarily nested depending on the generator type.
+    #[serde(with = "nbt_serde::value")]
+    generator: nbt::Value,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DataPacks {
+    enabled: Vec<String>,
+    disabled: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Version {
+    id: i32,
+    name: String,
+    series: String,
+    snapshot: bool,
+}
+
+impl LevelData {
+    /// Reads a gzip compressed `level.dat`.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self, McaError> {
+        read_gzip_nbt::<LevelDat>(path.as_ref()).map(|level| level.data)
+    }
+
+    /// Writes the level data back out as a gzip compressed `level.dat`.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), McaError> {
+        // Serializing needs the `Data` wrapper without taking ownership of self.
+        #[derive(Serialize)]
+        struct LevelDatRef<'a> {
+            #[serde(rename = "Data")]
+            data: &'a LevelData,
+        }
+        write_gzip_nbt(path.as_ref(), &LevelDatRef { data: self })
+    }
+
+    /// Data version of the game that last saved the world, `None` before 1.9.
+    pub fn data_version(&self) -> Option<i32> {
+        self.data_version
+    }
+
+    /// NBT format version, 19132 for worlds still stored in McRegion `.mcr` files.
+    pub fn nbt_version(&self) -> Option<i32> {
+        self.nbt_version
+    }
+
+    pub fn level_name(&self) -> &str {
+        &self.level_name
+    }
+
+    pub fn set_level_name(&mut self, level_name: String) {
+        self.level_name = level_name;
+    }
+
+    pub fn spawn(&self) -> Vec3I {
+        Vec3I::new(self.spawn_x, self.spawn_y, self.spawn_z)
+    }
+
+    pub fn set_spawn(&mut self, spawn: Vec3I) {
+        self.spawn_x = spawn.x();
+        self.spawn_y = spawn.y();
+        self.spawn_z = spawn.z();
+    }
+
+    /// World seed, read from the world gen settings or the pre 1.16 `RandomSeed`.
+    pub fn seed(&self) -> Option<i64> {
+        self.world_gen_settings
+            .as_ref()
+            .map(|settings| settings.seed)
+            .or(self.random_seed)
+    }
+
+    pub fn world_gen_settings(&self) -> Option<&WorldGenSettings> {
+        self.world_gen_settings.as_ref()
+    }
+
+    pub fn game_rules(&self) -> &HashMap<String, String> {
+        &self.game_rules
+    }
+
+    pub fn game_rule(&self, name: &str) -> Option<&str> {
+        self.game_rules.get(name).map(String::as_str)
+    }
+
+    pub fn set_game_rule(&mut self, name: String, value: String) {
+        self.game_rules.insert(name, value);
+    }
+
+    pub fn enabled_data_packs(&self) -> &[String] {
+        self.data_packs
+            .as_ref()
+            .map(|data_packs| &data_packs.enabled[..])
+            .unwrap_or_default()
+    }
+
+    pub fn disabled_data_packs(&self) -> &[String] {
+        self.data_packs
+            .as_ref()
+            .map(|data_packs| &data_packs.disabled[..])
+            .unwrap_or_default()
+    }
+
+    /// Name of the game version that last saved the world, e.g. "1.20.4".
+    pub fn version_name(&self) -> Option<&str> {
+        self.version.as_ref().map(|version| version.name.as_str())
+    }
+
+    pub fn version(&self) -> Option<&Version> {
+        self.version.as_ref()
+    }
+
+    /// Total ticks the world has been running.
+    pub fn time(&self) -> i64 {
+        self.time
+    }
+
+    /// Time of day in ticks, keeps counting up past 24000.
+    pub fn day_time(&self) -> i64 {
+        self.day_time
+    }
+
+    pub fn is_raining(&self) -> bool {
+        self.raining
+    }
+
+    pub fn is_thundering(&self) -> bool {
+        self.thundering
+    }
+
+    pub fn is_hardcore(&self) -> bool {
+        self.hardcore
+    }
+}
+
+impl WorldGenSettings {
+    pub fn seed(&self) -> i64 {
+        self.seed
+    }
+
+    pub fn generate_features(&self) -> bool {
+        self.generate_features
+    }
+
+    pub fn bonus_chest(&self) -> bool {
+        self.bonus_chest
+    }
+
+    pub fn dimensions(&self) -> &HashMap<String, DimensionGenerator> {
+        &self.dimensions
+    }
+}
+
+impl DimensionGenerator {
+    pub fn dimension_type(&self) -> &str {
+        &self.typ
+    }
+
+    pub fn generator(&self) -> &nbt::Value {
+        &self.generator
+    }
+}
+
+impl Version {
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn series(&self) -> &str {
+        &self.series
+    }
+
+    pub fn is_snapshot(&self) -> bool {
+        self.snapshot
+    }
+}
+
+#[test]
+fn test_level_round_trip() {
+    // A 1.8 level.dat, from before `DataVersion` and `Version` were added.
+    let level = LevelData::read("fixtures/level-1.8.dat").unwrap();
+    assert_eq!(level.data_version(), None);
+    assert_eq!(level.nbt_version(), Some(19133));
+    assert_eq!(level.level_name(), "Fixture");
+    assert_eq!(level.seed(), Some(42));
+    assert_eq!(level.game_rule("randomTickSpeed"), Some("3"));
+    assert!(level.extra.contains_key("generatorName"));
+    assert!(level.player.is_some());
+
+    // The fixture also has array tags a mod might add, the rewritten file has to hold exactly
+    // the same tags with the same types.
+    let path = crate::TempPath::new("level.dat");
+    level.write(&path).unwrap();
+    let raw = |path: &Path| {
+        let bytes = crate::dat::read_gzip_bytes(path).unwrap();
+        nbt::Blob::from_reader(&mut &bytes[..]).unwrap()
+    };
+    assert_eq!(raw(&path), raw(Path::new("fixtures/level-1.8.dat")));
+}
@@ -0,0 +1,97 @@
+mod arrays;
+mod bb;
+mod block_iter;
+mod block_state;
+mod blocks;
+mod dat;
+mod entities;
+mod error;
+mod heightmap;
+mod legacy;
+mod legacy_ids;
+mod level;
+mod light;
+mod light_engine;
+mod lz4;
+mod mca;
+mod nbt_serde;
+mod packed;
+mod player;
+mod poi;
+mod pos;
+mod region;
+mod registry;
+mod stats;
+mod uuid;
+mod vec;
+mod view;
+mod world;
+mod writer;
+
+pub use crate::{
+    bb::BoundingBox,
+    block_iter::{BlockFilter, BlockPattern, Blocks, ChunksBlocks, InvalidBlockPattern},
+    block_state::{BlockState, InvalidBlockState, Symbol},
+    entities::{Attribute, Entity, EntityChunk, Item, PotionEffect},
+    error::McaError,
+    heightmap::HeightmapKind,
+    legacy_ids::legacy_block_state,
+    level::{DataPacks, DimensionGenerator, LevelData, Version, WorldGenSettings},
+    light::LightKind,
+    light_engine::relight_chunks,
+    mca::{Chunks, CompressionScheme, RegionFile, UnsupportedCompression},
+    player::{Abilities, PlayerData, PlayerDataStore, RecipeBook},
+    poi::{PoiChunk, PoiRecord, PoiSection},
+    pos::{BlockPos, ChunkPos},
+    region::{BlockStatePalette, RegionChunk, Section},
+    registry::{Registry, ValidationError},
+    stats::{BlockCount, BlockStats},
+    uuid::Uuid,
+    vec::{Vec2F, Vec2I, Vec3D, Vec3I},
+    view::WorldView,
+    world::{Dimension, DimensionChunks, RegionPath, StorageKind, World},
+    writer::RegionWriter,
+};
+
+/// A unique path in the temp directory for tests, removed when dropped so it's cleaned up even
+/// when the test fails.
+#[cfg(test)]
+pub(crate) struct TempPath(std::path::PathBuf);
+
+#[cfg(test)]
+impl TempPath {
+    pub(crate) fn new(name: &str) -> Self {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let unique = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let name = format!("mca-rs-{}-{}-{}", std::process::id(), unique, name);
+        Self(std::env::temp_dir().join(name))
+    }
+}
+
+#[cfg(test)]
+impl std::ops::Deref for TempPath {
+    type Target = std::path::Path;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl AsRef<std::path::Path> for TempPath {
+    fn as_ref(&self) -> &std::path::Path {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        if self.0.is_dir() {
+            let _ = std::fs::remove_dir_all(&self.0);
+        } else {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+}
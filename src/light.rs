@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+
+// A section stores one nibble per block.
+const NIBBLE_ARRAY_LEN: usize = 2048;
+
+/// The two kinds of light a section stores.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LightKind {
+    // Light from torches, lava, glowstone and other emitting blocks (`BlockLight`).
+    Block,
+    // Light from the sky (`SkyLight`), only stored in dimensions with a sky.
+    Sky,
+}
+
+/// A light level (0-15) for each of the 4096 blocks of a section, packed two per byte with the
+/// even index in the low nibble.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub(crate) struct NibbleArray(#[serde(serialize_with = "nbt::i8_array")] Vec<i8>);
+
+impl NibbleArray {
+    pub(crate) fn new(level: u8) -> Self {
+        let level = level & 0xF;
+        Self(vec![(level | level << 4) as i8; NIBBLE_ARRAY_LEN])
+    }
+
+    /// Level at `index` (`y * 256 + z * 16 + x`), 0 if the array is too short.
+    pub(crate) fn get(&self, index: usize) -> u8 {
+        self.0
+            .get(index >> 1)
+            .map(|byte| (*byte as u8 >> ((index & 1) * 4)) & 0xF)
+            .unwrap_or(0)
+    }
+
+    pub(crate) fn set(&mut self, index: usize, level: u8) {
+        if self.0.len() < NIBBLE_ARRAY_LEN {
+            self.0.resize(NIBBLE_ARRAY_LEN, 0);
+        }
+        let shift = (index & 1) * 4;
+        let byte = &mut self.0[index >> 1];
+        *byte = ((*byte as u8 & !(0xF << shift)) | ((level & 0xF) << shift)) as i8;
+    }
+}
+
+#[test]
+fn test_nibble_array() {
+    let mut light = NibbleArray::new(0);
+    light.set(0, 15);
+    light.set(1, 7);
+    light.set(4095, 3);
+    assert_eq!(light.get(0), 15);
+    assert_eq!(light.get(1), 7);
+    assert_eq!(light.get(2), 0);
+    assert_eq!(light.get(4095), 3);
+    assert_eq!(light.0[0] as u8, 0x7F);
+}
@@ -0,0 +1,323 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::{
+    blocks,
+    light::{LightKind, NibbleArray},
+    pos::ChunkPos,
+    region::RegionChunk,
+};
+
+const MAX_LIGHT: u8 = 15;
+
+const NEIGHBOURS: [(i32, i32, i32); 6] = [
+    (1, 0, 0),
+    (-1, 0, 0),
+    (0, 1, 0),
+    (0, -1, 0),
+    (0, 0, 1),
+    (0, 0, -1),
+];
+
+/// Recomputes block light, and sky light if `has_sky` is set, for a group of chunks. Light
+/// spreads between the chunks of the group, chunks outside of it are treated as unlit. For
+/// correct light at the edges of an edited area include its neighbouring chunks in the group.
+///
+/// The results replace the `BlockLight` and `SkyLight` arrays of every section and mark the
+/// chunks as lit.
+pub fn relight_chunks<'a, I>(chunks: I, has_sky: bool)
+where
+    I: IntoIterator<Item = &'a mut RegionChunk>,
+{
+    let mut chunks: Vec<&mut RegionChunk> = chunks.into_iter().collect();
+    if chunks.is_empty() {
+        return;
+    }
+    let mut volume = Volume::new(&chunks);
+
+    let mut queue = VecDeque::new();
+    volume.seed_block_light(&mut queue);
+    volume.propagate(LightKind::Block, queue);
+    if has_sky {
+        let mut queue = VecDeque::new();
+        volume.seed_sky_light(&mut queue);
+        volume.propagate(LightKind::Sky, queue);
+    }
+
+    for (chunk, light) in chunks.iter_mut().zip(&volume.chunks) {
+        volume.write_back(chunk, light, has_sky);
+    }
+}
+
+// Light, opacity and emission for every block of a chunk, indexed by `y * 256 + z * 16 + x`
+// with y counted from the bottom of the volume.
+struct ChunkLight {
+    pos: ChunkPos,
+    opacity: Vec<u8>,
+    emission: Vec<u8>,
+    block_light: Vec<u8>,
+    sky_light: Vec<u8>,
+}
+
+impl ChunkLight {
+    fn levels(&self, kind: LightKind) -> &[u8] {
+        match kind {
+            LightKind::Block => &self.block_light,
+            LightKind::Sky => &self.sky_light,
+        }
+    }
+
+    fn levels_mut(&mut self, kind: LightKind) -> &mut [u8] {
+        match kind {
+            LightKind::Block => &mut self.block_light,
+            LightKind::Sky => &mut self.sky_light,
+        }
+    }
+}
+
+// The chunks being lit, all sharing the same vertical range.
+struct Volume {
+    chunks: Vec<ChunkLight>,
+    positions: HashMap<ChunkPos, usize>,
+    min_y: i32,
+    height: i32,
+}
+
+impl Volume {
+    fn new(chunks: &[&mut RegionChunk]) -> Self {
+        let min_y = chunks.iter().map(|chunk| chunk.min_y()).min().unwrap_or(0);
+        let max_y = chunks
+            .iter()
+            .flat_map(|chunk| chunk.sections())
+            .map(|section| section.y() as i32 * 16 + 16)
+            .max()
+            .unwrap_or(min_y);
+        let height = (max_y - min_y).max(0);
+        let len = height as usize * 256;
+
+        let mut light_chunks = Vec::with_capacity(chunks.len());
+        let mut positions = HashMap::new();
+        for chunk in chunks {
+            // Missing sections are air.
+            let mut opacity = vec![0; len];
+            let mut emission = vec![0; len];
+            for section in chunk.sections() {
+                let offset = match section_offset(section.y(), min_y, height) {
+                    Some(offset) => offset,
+                    None => continue,
+                };
+                // Classify each palette entry once instead of every block.
+                let palette_opacity: Vec<u8> = section
+                    .block_palette()
+                    .iter()
+                    .map(blocks::light_opacity)
+                    .collect();
+                let palette_emission: Vec<u8> = section
+                    .block_palette()
+                    .iter()
+                    .map(blocks::light_emission)
+                    .collect();
+                for (i, palette_index) in section.decode().iter().enumerate() {
+                    let palette_index = *palette_index as usize;
+                    opacity[offset + i] = palette_opacity.get(palette_index).copied().unwrap_or(0);
+                    emission[offset + i] =
+                        palette_emission.get(palette_index).copied().unwrap_or(0);
+                }
+            }
+            positions.insert(chunk.pos(), light_chunks.len());
+            light_chunks.push(ChunkLight {
+                pos: chunk.pos(),
+                opacity,
+                emission,
+                block_light: vec![0; len],
+                sky_light: vec![0; len],
+            });
+        }
+        Self {
+            chunks: light_chunks,
+            positions,
+            min_y,
+            height,
+        }
+    }
+
+    fn seed_block_light(&mut self, queue: &mut VecDeque<(usize, usize)>) {
+        for (chunk_index, chunk) in self.chunks.iter_mut().enumerate() {
+            for (i, emission) in chunk.emission.iter().enumerate() {
+                if *emission > 0 {
+                    chunk.block_light[i] = *emission;
+                    queue.push_back((chunk_index, i));
+                }
+            }
+        }
+    }
+
+    // Sky light shines straight down at full strength until something dims it.
+    fn seed_sky_light(&mut self, queue: &mut VecDeque<(usize, usize)>) {
+        let height = self.height as usize;
+        for (chunk_index, chunk) in self.chunks.iter_mut().enumerate() {
+            for column in 0..256 {
+                let mut level = MAX_LIGHT;
+                for y in (0..height).rev() {
+                    let i = y * 256 + column;
+                    level = level.saturating_sub(chunk.opacity[i]);
+                    if level == 0 {
+                        break;
+                    }
+                    chunk.sky_light[i] = level;
+                    queue.push_back((chunk_index, i));
+                }
+            }
+        }
+    }
+
+    // Breadth first flood fill, each step loses at least one level.
+    fn propagate(&mut self, kind: LightKind, mut queue: VecDeque<(usize, usize)>) {
+        while let Some((chunk_index, i)) = queue.pop_front() {
+            let level = self.chunks[chunk_index].levels(kind)[i];
+            if level <= 1 {
+                continue;
+            }
+            for offset in NEIGHBOURS {
+                let (neighbour_chunk, neighbour) = match self.neighbour(chunk_index, i, offset) {
+                    Some(neighbour) => neighbour,
+                    None => continue,
+                };
+                let chunk = &mut self.chunks[neighbour_chunk];
+                let new_level = level.saturating_sub(chunk.opacity[neighbour].max(1));
+                let levels = chunk.levels_mut(kind);
+                if new_level > levels[neighbour] {
+                    levels[neighbour] = new_level;
+                    queue.push_back((neighbour_chunk, neighbour));
+                }
+            }
+        }
+    }
+
+    fn neighbour(
+        &self,
+        chunk_index: usize,
+        i: usize,
+        offset: (i32, i32, i32),
+    ) -> Option<(usize, usize)> {
+        let (x, y, z) = ((i % 16) as i32, (i / 256) as i32, ((i / 16) % 16) as i32);
+        let (x, y, z) = (x + offset.0, y + offset.1, z + offset.2);
+        if y < 0 || y >= self.height {
+            return None;
+        }
+        let chunk_index = if (0..16).contains(&x) && (0..16).contains(&z) {
+            chunk_index
+        } else {
+            let pos = self.chunks[chunk_index].pos;
+            let neighbour = ChunkPos::new(pos.x() + x.div_euclid(16), pos.z() + z.div_euclid(16));
+            *self.positions.get(&neighbour)?
+        };
+        let i = y as usize * 256 + z.rem_euclid(16) as usize * 16 + x.rem_euclid(16) as usize;
+        Some((chunk_index, i))
+    }
+
+    fn write_back(&self, chunk: &mut RegionChunk, light: &ChunkLight, has_sky: bool) {
+        for section in chunk.sections_mut() {
+            let offset = match section_offset(section.y(), self.min_y, self.height) {
+                Some(offset) => offset,
+                None => continue,
+            };
+            for kind in [LightKind::Block, LightKind::Sky] {
+                if kind == LightKind::Sky && !has_sky {
+                    *section.light_mut(kind) = None;
+                    continue;
+                }
+                let mut nibbles = NibbleArray::new(0);
+                for (i, level) in light.levels(kind)[offset..offset + 4096].iter().enumerate() {
+                    nibbles.set(i, *level);
+                }
+                *section.light_mut(kind) = Some(nibbles);
+            }
+        }
+        chunk.set_light_on(true);
+    }
+}
+
+// Index of the first block of a section within a chunk's light arrays.
+fn section_offset(section_y: i8, min_y: i32, height: i32) -> Option<usize> {
+    let y = section_y as i32 * 16 - min_y;
+    if y < 0 || y + 16 > height {
+        return None;
+    }
+    Some(y as usize * 256)
+}
+
+#[test]
+fn test_light_falloff() {
+    let mut light = ChunkLight {
+        pos: ChunkPos::new(0, 0),
+        opacity: vec![0; 4096],
+        emission: vec![0; 4096],
+        block_light: vec![0; 4096],
+        sky_light: vec![0; 4096],
+    };
+    // A torch in the middle of an empty section with a stone block next to it.
+    light.emission[8 * 256 + 8 * 16 + 8] = 14;
+    light.opacity[8 * 256 + 8 * 16 + 9] = 15;
+    let mut volume = Volume {
+        chunks: vec![light],
+        positions: HashMap::from([(ChunkPos::new(0, 0), 0)]),
+        min_y: 0,
+        height: 16,
+    };
+    let mut queue = VecDeque::new();
+    volume.seed_block_light(&mut queue);
+    volume.propagate(LightKind::Block, queue);
+    let block_light = &volume.chunks[0].block_light;
+    assert_eq!(block_light[8 * 256 + 8 * 16 + 8], 14);
+    assert_eq!(block_light[8 * 256 + 8 * 16 + 7], 13);
+    assert_eq!(block_light[8 * 256 + 8 * 16 + 9], 0);
+    assert_eq!(block_light[8 * 256 + 8 * 16 + 2], 8);
+}
+
+#[test]
+fn test_relight_chunks() {
+    use crate::region::BlockStatePalette;
+
+    fn set_block(chunk: &mut RegionChunk, x: i32, y: i32, z: i32, name: &str) {
+        let section = chunk.section_mut((y >> 4) as i8).unwrap();
+        let (x, y, z) = (x.rem_euclid(16), y.rem_euclid(16), z.rem_euclid(16));
+        section.set_block(
+            x as usize,
+            y as usize,
+            z as usize,
+            BlockStatePalette::new(name),
+        );
+    }
+
+    let mut west = RegionChunk::empty(ChunkPos::new(0, 0));
+    let mut east = RegionChunk::empty(ChunkPos::new(1, 0));
+    // A torch against the east edge of the first chunk, in the bottom block of its section.
+    set_block(&mut west, 15, 64, 8, "minecraft:torch");
+    // A stone roof over x 20 to 28 and z 0 to 8 in the second chunk.
+    for x in 20..=28 {
+        for z in 0..=8 {
+            set_block(&mut east, x, 100, z, "minecraft:stone");
+        }
+    }
+    relight_chunks([&mut west, &mut east], true);
+
+    assert!(west.is_light_on() && east.is_light_on());
+    let block = |chunk: &RegionChunk, x, y, z| chunk.light_at(LightKind::Block, x, y, z);
+    let sky = |chunk: &RegionChunk, x, y, z| chunk.light_at(LightKind::Sky, x, y, z);
+    assert_eq!(block(&west, 15, 64, 8), Some(14));
+    // Torch light crosses into the neighbouring chunk.
+    assert_eq!(block(&east, 16, 64, 8), Some(13));
+    assert_eq!(block(&east, 18, 64, 8), Some(11));
+    assert_eq!(block(&east, 31, 64, 8), Some(0));
+    // And into the section below.
+    assert_eq!(block(&west, 15, 63, 8), Some(13));
+    assert_eq!(block(&west, 15, 60, 8), Some(10));
+    // Under the middle of the roof the nearest open sky is 5 blocks away on three sides, the
+    // chunk to the north isn't part of the group and counts as unlit.
+    assert_eq!(sky(&east, 24, 101, 4), Some(15));
+    assert_eq!(sky(&east, 24, 100, 4), Some(0));
+    assert_eq!(sky(&east, 24, 99, 4), Some(10));
+    assert_eq!(sky(&east, 24, 90, 4), Some(10));
+    assert_eq!(sky(&east, 19, 99, 4), Some(15));
+    assert_eq!(sky(&west, 8, 0, 8), Some(15));
+}
@@ -0,0 +1,229 @@
+use std::{
+    cmp,
+    hash::Hasher,
+    io::{self, Read, Write},
+};
+
+use twox_hash::XxHash32;
+
+// The game compresses chunks with lz4-java's `LZ4BlockOutputStream`, which wraps raw LZ4 blocks
+// in its own framing rather than the standard LZ4 frame format. Every block starts with a
+// 21 byte header:
+//   magic "LZ4Block" (8 bytes)
+//   token (1 byte): compression method in the upper nibble, log2(block size) - 10 in the lower
+//   compressed length (4 bytes, little endian)
+//   decompressed length (4 bytes, little endian)
+//   checksum of the decompressed bytes (4 bytes, little endian)
+// The stream is terminated by an empty block.
+const MAGIC: &[u8; 8] = b"LZ4Block";
+const HEADER_LENGTH: usize = MAGIC.len() + 1 + 4 + 4 + 4;
+const METHOD_RAW: u8 = 0x10;
+const METHOD_LZ4: u8 = 0x20;
+const COMPRESSION_LEVEL_BASE: u8 = 10;
+// 64KiB blocks, the default used by the game.
+const COMPRESSION_LEVEL: u8 = 6;
+const BLOCK_SIZE: usize = 1 << (COMPRESSION_LEVEL_BASE + COMPRESSION_LEVEL);
+const CHECKSUM_SEED: u32 = 0x9747b28c;
+
+fn checksum(bytes: &[u8]) -> u32 {
+    let mut hasher = XxHash32::with_seed(CHECKSUM_SEED);
+    hasher.write(bytes);
+    // lz4-java only keeps the lower 28 bits of the hash.
+    (hasher.finish() as u32) & 0x0FFF_FFFF
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+pub(crate) struct Lz4BlockDecoder<R> {
+    reader: R,
+    buffer: Vec<u8>,
+    position: usize,
+    finished: bool,
+}
+
+impl<R: Read> Lz4BlockDecoder<R> {
+    pub(crate) fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buffer: Vec::new(),
+            position: 0,
+            finished: false,
+        }
+    }
+
+    fn read_block(&mut self) -> io::Result<()> {
+        let mut header = [0u8; HEADER_LENGTH];
+        match self.reader.read_exact(&mut header) {
+            Ok(()) => {}
+            // Be lenient with streams that end on a block boundary without the empty end block.
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                self.finished = true;
+                return Ok(());
+            }
+            Err(err) => return Err(err),
+        }
+        if &header[..MAGIC.len()] != MAGIC {
+            return Err(invalid_data("LZ4 block is missing its magic header"));
+        }
+        let token = header[8];
+        let method = token & 0xF0;
+        let block_size = 1usize << (COMPRESSION_LEVEL_BASE + (token & 0x0F));
+        let read_u32 = |offset: usize| {
+            u32::from_le_bytes([
+                header[offset],
+                header[offset + 1],
+                header[offset + 2],
+                header[offset + 3],
+            ])
+        };
+        let compressed_length = read_u32(9) as usize;
+        let decompressed_length = read_u32(13) as usize;
+        let check = read_u32(17);
+        // Check the lengths before allocating, they come straight from the file.
+        if decompressed_length > block_size {
+            return Err(invalid_data("LZ4 block is larger than its block size"));
+        }
+        if compressed_length > lz4_flex::block::get_maximum_output_size(block_size) {
+            return Err(invalid_data("LZ4 block exceeds the compressed bound"));
+        }
+        if decompressed_length == 0 && compressed_length == 0 {
+            // Empty block marks the end of the stream.
+            self.finished = true;
+            return Ok(());
+        }
+        let mut compressed = vec![0u8; compressed_length];
+        self.reader.read_exact(&mut compressed)?;
+        self.buffer = match method {
+            METHOD_RAW if compressed_length == decompressed_length => compressed,
+            METHOD_LZ4 => lz4_flex::block::decompress(&compressed, decompressed_length)
+                .map_err(|err| invalid_data(&err.to_string()))?,
+            _ => return Err(invalid_data("Unknown LZ4 block compression method")),
+        };
+        if self.buffer.len() != decompressed_length {
+            return Err(invalid_data("LZ4 block decompressed to the wrong length"));
+        }
+        if checksum(&self.buffer) != check {
+            return Err(invalid_data("LZ4 block checksum mismatch"));
+        }
+        self.position = 0;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for Lz4BlockDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.buffer.len() {
+            if self.finished {
+                return Ok(0);
+            }
+            self.read_block()?;
+        }
+        let amount = cmp::min(buf.len(), self.buffer.len() - self.position);
+        buf[..amount].copy_from_slice(&self.buffer[self.position..self.position + amount]);
+        self.position += amount;
+        Ok(amount)
+    }
+}
+
+pub(crate) struct Lz4BlockEncoder<W: Write> {
+    writer: W,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> Lz4BlockEncoder<W> {
+    pub(crate) fn new(writer: W) -> Self {
+        Self {
+            writer,
+            buffer: Vec::with_capacity(BLOCK_SIZE),
+        }
+    }
+
+    /// Writes out any buffered data followed by the end of stream marker.
+    pub(crate) fn finish(mut self) -> io::Result<W> {
+        self.write_block()?;
+        self.write_header(METHOD_RAW, 0, 0, 0)?;
+        Ok(self.writer)
+    }
+
+    fn write_block(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let check = checksum(&self.buffer);
+        let compressed = lz4_flex::block::compress(&self.buffer);
+        // Blocks that don't shrink are stored as is.
+        if compressed.len() < self.buffer.len() {
+            self.write_header(METHOD_LZ4, compressed.len(), self.buffer.len(), check)?;
+            self.writer.write_all(&compressed)?;
+        } else {
+            self.write_header(METHOD_RAW, self.buffer.len(), self.buffer.len(), check)?;
+            self.writer.write_all(&self.buffer)?;
+        }
+        self.buffer.clear();
+        Ok(())
+    }
+
+    fn write_header(
+        &mut self,
+        method: u8,
+        compressed_length: usize,
+        decompressed_length: usize,
+        check: u32,
+    ) -> io::Result<()> {
+        let mut header = [0u8; HEADER_LENGTH];
+        header[..MAGIC.len()].copy_from_slice(MAGIC);
+        header[8] = method | COMPRESSION_LEVEL;
+        header[9..13].copy_from_slice(&(compressed_length as u32).to_le_bytes());
+        header[13..17].copy_from_slice(&(decompressed_length as u32).to_le_bytes());
+        header[17..21].copy_from_slice(&check.to_le_bytes());
+        self.writer.write_all(&header)
+    }
+}
+
+impl<W: Write> Write for Lz4BlockEncoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let amount = cmp::min(buf.len(), BLOCK_SIZE - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..amount]);
+        if self.buffer.len() == BLOCK_SIZE {
+            self.write_block()?;
+        }
+        Ok(amount)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_block()?;
+        self.writer.flush()
+    }
+}
+
+#[test]
+fn test_lz4_round_trip() {
+    // Spans multiple blocks and mixes compressible with incompressible data.
+    let mut bytes: Vec<u8> = (0..100_000u32).map(|i| (i % 7) as u8).collect();
+    bytes.extend((0..100_000u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8));
+
+    let mut encoder = Lz4BlockEncoder::new(Vec::new());
+    encoder.write_all(&bytes).unwrap();
+    let compressed = encoder.finish().unwrap();
+
+    let mut decompressed = Vec::new();
+    Lz4BlockDecoder::new(&compressed[..])
+        .read_to_end(&mut decompressed)
+        .unwrap();
+    assert_eq!(bytes, decompressed);
+}
+
+#[test]
+fn test_lz4_rejects_oversized_block() {
+    let mut header = MAGIC.to_vec();
+    header.push(METHOD_LZ4 | COMPRESSION_LEVEL);
+    header.extend(u32::MAX.to_le_bytes());
+    header.extend(16u32.to_le_bytes());
+    header.extend(0u32.to_le_bytes());
+    let err = Lz4BlockDecoder::new(&header[..])
+        .read_to_end(&mut Vec::new())
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
@@ -1,153 +1,300 @@
-mod arrays;
-mod entities;
-mod region;
-mod bb;
-mod uuid;
-mod vec;
 use std::{
-    fs::{self, File},
-    io::{Read, Write},
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+    process::ExitCode,
 };
 
-use flate2::bufread::{GzDecoder, ZlibDecoder};
-use nbt::error;
-use serde::Deserialize;
-use vec::Vec3D;
+use clap::{Parser, Subcommand, ValueEnum};
+use mca_rs::{BlockFilter, BlockPattern, BlockStats, Dimension, RegionFile, World};
 
-use crate::{entities::EntityChunk, region::RegionChunk};
-
-#[derive(Debug)]
-struct ChunkInfo {
-    // Offset of where the chunk is located in the file.
-    chunk_offset_bytes: usize,
-    // Size of the chunk.
-    size: usize,
-    // Timestamp of the last time the chunk was modified.
-    timestamp: u32,
+#[derive(Parser)]
+#[command(name = "mca", about = "Inspect Minecraft region files and worlds")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
 }
 
-#[derive(Debug)]
-struct ChunkHeader {
-    length: u32,
-    compression_scheme: CompressionScheme,
+#[derive(Subcommand)]
+enum Command {
+    /// Summary of a region file.
+    Info { region: PathBuf },
+    /// List the generated chunks in a region with their size and timestamp.
+    List { region: PathBuf },
+    /// Print a single chunk, `x` and `z` are relative to the region (0-31).
+    Dump {
+        region: PathBuf,
+        x: usize,
+        z: usize,
+        #[arg(short, long, value_enum, default_value_t = Format::Pretty)]
+        format: Format,
+    },
+    /// Write the uncompressed NBT of a single chunk to a file.
+    Extract {
+        region: PathBuf,
+        x: usize,
+        z: usize,
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Search a world for blocks, printing the coordinates of every match.
+    FindBlocks {
+        world: PathBuf,
+        /// Block to look for with an optional subset of its properties, e.g.
+        /// `minecraft:chest[type=single]`. Can be given multiple times.
+        #[arg(short, long = "block", required = true)]
+        blocks: Vec<BlockPattern>,
+        /// Only search this dimension, e.g. `overworld` or `minecraft:the_nether`.
+        #[arg(short, long)]
+        dim: Option<String>,
+        #[arg(long, allow_hyphen_values = true)]
+        min_y: Option<i32>,
+        #[arg(long, allow_hyphen_values = true)]
+        max_y: Option<i32>,
+        /// `json` prints a JSON object per match and line as they are found (JSON Lines).
+        #[arg(short, long, value_enum, default_value_t = TableFormat::Csv)]
+        format: TableFormat,
+    },
+    /// Count blocks by name for every dimension and Y level of a world.
+    BlockStats {
+        world: PathBuf,
+        /// Only count this dimension, e.g. `overworld` or `minecraft:the_nether`.
+        #[arg(short, long)]
+        dim: Option<String>,
+        /// Count blocks per biome as well.
+        #[arg(long)]
+        by_biome: bool,
+        #[arg(short, long, value_enum, default_value_t = TableFormat::Csv)]
+        format: TableFormat,
+    },
 }
 
-impl From<[u8; 5]> for ChunkHeader {
-    fn from(value: [u8; 5]) -> Self {
-        Self {
-            length: u32::from_be_bytes(value[0..4].try_into().unwrap()),
-            compression_scheme: CompressionScheme::from(value[4]),
-        }
-    }
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Pretty,
+    Json,
 }
 
-#[derive(Debug)]
-enum CompressionScheme {
-    Gzip,
-    Zlib,
+#[derive(Clone, Copy, ValueEnum)]
+enum TableFormat {
+    Csv,
+    Json,
 }
 
-impl From<u8> for CompressionScheme {
-    fn from(value: u8) -> Self {
-        match value {
-            1 => CompressionScheme::Gzip,
-            2 => CompressionScheme::Zlib,
-            _ => unreachable!("Unknown compression scheme {}", value),
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli.command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::FAILURE
         }
     }
 }
 
-#[test]
-fn test() {
-    extern crate uuid as test;
-    use std::io::Cursor;
-    use test::Uuid as uuid_parser;
-    use uuid::Uuid;
-    let x = "279afc35-e8a9-4927-adb0-19b33499ec6c";
-    let uuid = Uuid::new(x);
-    let x = uuid.to_u128();
-    println!("UUID: {:#?}", x);
-    println!("UUID: {:#?}", uuid_parser::from_u128(x));
-
-    let filename = "output.nbt";
-    let bytes = fs::read(filename).unwrap();
-    let cursor = Cursor::new(bytes);
-    let x: EntityChunk = nbt::from_reader(cursor).unwrap();
-    println!("Here: {:#?}", x);
+fn run(command: Command) -> Result<(), Box<dyn std::error::Error>> {
+    match command {
+        Command::Info { region } => info(RegionFile::open(region)?),
+        Command::List { region } => list(RegionFile::open(region)?),
+        Command::Dump {
+            region,
+            x,
+            z,
+            format,
+        } => dump(RegionFile::open(region)?, x, z, format),
+        Command::Extract {
+            region,
+            x,
+            z,
+            output,
+        } => {
+            let region = RegionFile::open(region)?;
+            let bytes = region
+                .chunk_data(x, z)?
+                .ok_or_else(|| missing_chunk(&region, x, z))?;
+            fs::write(output, bytes)?;
+            Ok(())
+        }
+        Command::FindBlocks {
+            world,
+            blocks,
+            dim,
+            min_y,
+            max_y,
+            format,
+        } => {
+            let world = World::open(world)?;
+            let dimensions = selected_dimensions(&world, dim.as_deref())?;
+            let mut filter = blocks
+                .into_iter()
+                .fold(BlockFilter::new(), BlockFilter::with_pattern);
+            if min_y.is_some() || max_y.is_some() {
+                filter = filter.with_y_range(min_y.unwrap_or(i32::MIN), max_y.unwrap_or(i32::MAX));
+            }
+            find_blocks(&dimensions, filter, format)
+        }
+        Command::BlockStats {
+            world,
+            dim,
+            by_biome,
+            format,
+        } => {
+            let world = World::open(world)?;
+            let mut stats = if by_biome {
+                BlockStats::by_biome()
+            } else {
+                BlockStats::new()
+            };
+            for dimension in selected_dimensions(&world, dim.as_deref())? {
+                for err in stats.add_dimension(dimension)? {
+                    eprintln!("warning: {}", err);
+                }
+            }
+            let mut stdout = io::stdout().lock();
+            match format {
+                TableFormat::Csv => stats.write_csv(&mut stdout)?,
+                TableFormat::Json => {
+                    stats.write_json(&mut stdout)?;
+                    writeln!(stdout)?;
+                }
+            }
+            Ok(())
+        }
+    }
 }
 
-fn main() {
-    // let filename = "r.0.-1.mca";
-    // parse_mca::<EntityChunk>(filename).unwrap();
+fn info(region: RegionFile) -> Result<(), Box<dyn std::error::Error>> {
+    let positions: Vec<_> = region.local_positions().collect();
+    let size: usize = positions
+        .iter()
+        .filter_map(|(x, z)| region.chunk_size(*x, *z))
+        .sum();
+    let timestamps = positions
+        .iter()
+        .filter_map(|(x, z)| region.chunk_timestamp(*x, *z));
+    println!("Path: {}", region.path().display());
+    match region.coordinates() {
+        Some((x, z)) => println!("Region: {}, {}", x, z),
+        None => println!("Region: unknown"),
+    }
+    println!("Chunks: {}", positions.len());
+    println!("Chunk data: {} bytes", size);
+    if let (Some(oldest), Some(newest)) = (timestamps.clone().min(), timestamps.max()) {
+        println!("Oldest chunk: {}", oldest);
+        println!("Newest chunk: {}", newest);
+    }
+    Ok(())
+}
 
-    let filename = "region/r.0.0.mca";
-    println!("{:#?}", parse_mca::<RegionChunk>(filename).unwrap());
+fn list(region: RegionFile) -> Result<(), Box<dyn std::error::Error>> {
+    println!("local_x\tlocal_z\tchunk_x\tchunk_z\tsize\ttimestamp");
+    for (x, z) in region.local_positions() {
+        let pos = region.chunk_pos(x, z);
+        println!(
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            x,
+            z,
+            pos.x(),
+            pos.z(),
+            region.chunk_size(x, z).unwrap_or(0),
+            region.chunk_timestamp(x, z).unwrap_or(0)
+        );
+    }
+    Ok(())
 }
 
-pub fn parse_mca<'de, T>(filename: &str) -> error::Result<Vec<T>>
-where
-    T: Deserialize<'de>,
-{
-    let bytes = fs::read(filename)?;
+fn dump(
+    region: RegionFile,
+    x: usize,
+    z: usize,
+    format: Format,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Read as raw NBT so the dump shows lists and arrays with the types they're stored as.
+    let data = region
+        .chunk_data(x, z)?
+        .ok_or_else(|| missing_chunk(&region, x, z))?;
+    let chunk = nbt::Blob::from_reader(&mut &data[..])?;
+    match format {
+        Format::Pretty => println!("{}", chunk),
+        Format::Json => println!("{}", serde_json::to_string_pretty(&chunk)?),
+    }
+    Ok(())
+}
 
-    let mut chunk_infos = Vec::new();
-    const CHUNK_SIZE: usize = 4096;
-    // The first 8KiB of the MCA file is the header which contains the location and timestamp tables for each chunk.
-    for (byte_offset, chunk_bytes) in bytes[0..CHUNK_SIZE].chunks(4).enumerate() {
-        let int_offset = byte_offset * 4;
-        let chunk_offset = u32::from_be_bytes([0, chunk_bytes[0], chunk_bytes[1], chunk_bytes[2]]);
-        let size = chunk_bytes[3];
-        // If chunk offset and size are 0 then the chunk hasn't been generated yet.
-        if chunk_offset != 0 && size != 0 {
-            // Should always be a 4 byte timestamp.
-            let timestamp_bytes = &bytes[(CHUNK_SIZE + int_offset)..(CHUNK_SIZE + int_offset + 4)];
-            let timestamp = u32::from_be_bytes(timestamp_bytes.try_into().expect(&format!(
-                "Only expected 4 bytes but got {}",
-                timestamp_bytes.len()
-            )));
-            chunk_infos.push(ChunkInfo {
-                chunk_offset_bytes: (chunk_offset as usize) * CHUNK_SIZE,
-                size: (size as usize) * CHUNK_SIZE,
-                timestamp,
-            });
+fn find_blocks(
+    dimensions: &[&Dimension],
+    filter: BlockFilter,
+    format: TableFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut stdout = io::stdout().lock();
+    if let TableFormat::Csv = format {
+        writeln!(stdout, "dimension,x,y,z,block")?;
+    }
+    for dimension in dimensions {
+        for block in dimension.blocks(filter.clone())? {
+            // A corrupt chunk shouldn't stop the search of the rest of the world.
+            let (pos, state) = match block {
+                Ok(block) => block,
+                Err(err) => {
+                    eprintln!("warning: {}", err);
+                    continue;
+                }
+            };
+            match format {
+                TableFormat::Csv => writeln!(
+                    stdout,
+                    "{},{},{},{},{}",
+                    csv_field(dimension.id()),
+                    pos.x(),
+                    pos.y(),
+                    pos.z(),
+                    csv_field(&state.to_string())
+                )?,
+                TableFormat::Json => {
+                    let found = serde_json::json!({
+                        "dimension": dimension.id(),
+                        "x": pos.x(),
+                        "y": pos.y(),
+                        "z": pos.z(),
+                        "block": state.to_string(),
+                    });
+                    serde_json::to_writer(&mut stdout, &found)?;
+                    writeln!(stdout)?;
+                }
+            }
         }
     }
-    let mut chunks: Vec<T> = Vec::with_capacity(chunk_infos.len());
-    for chunk_info in chunk_infos {
-        // Read first 5 bytes as chunk header
-        let mut current_offset = chunk_info.chunk_offset_bytes;
-        let header_bytes: [u8; 5] =
-            bytes[current_offset..current_offset + 5]
-                .try_into()
-                .or(Err(error::Error::Message(
-                    "Coult not slice the chunk header.".into(),
-                )))?;
-        current_offset += 5;
-        // Parse chunk header into meaningful parts
-        let header = ChunkHeader::from(header_bytes);
-        println!("Chunk: {:#?}", chunk_info);
-        println!("Header: {:#?}", header);
-        // Read from chunk header to chunk_header + chunk_length
-        let nbt_bytes = &bytes[current_offset..current_offset + header.length as usize];
-        let mut decompressed = Vec::new();
-        // Decode using the specified compression method
-        let mut reader = decompress_bytes_with_scheme(nbt_bytes, header.compression_scheme);
-        reader.read_to_end(&mut decompressed).unwrap();
-
-        let mut file = File::create("output.nbt").unwrap();
-        file.write_all(&decompressed).unwrap();
+    Ok(())
+}
 
-        chunks.push(nbt::from_slice(decompressed).unwrap());
+// Every dimension of the world, or just the one named on the command line.
+fn selected_dimensions<'a>(
+    world: &'a World,
+    id: Option<&str>,
+) -> Result<Vec<&'a Dimension>, String> {
+    match id {
+        Some(id) => world
+            .dimension(id)
+            .map(|dimension| vec![dimension])
+            .ok_or_else(|| format!("{}: no dimension named {}", world.path().display(), id)),
+        None => Ok(world.dimensions().iter().collect()),
     }
-    Ok(chunks)
 }
 
-fn decompress_bytes_with_scheme<'a>(
-    bytes: &'a [u8],
-    compression_scheme: CompressionScheme,
-) -> Box<dyn Read + 'a> {
-    match compression_scheme {
-        CompressionScheme::Gzip => Box::new(GzDecoder::new(bytes)),
-        CompressionScheme::Zlib => Box::new(ZlibDecoder::new(bytes)),
+// Quotes a CSV field if it contains a separator, block states with several properties do.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
     }
 }
+
+fn missing_chunk(region: &RegionFile, x: usize, z: usize) -> String {
+    format!(
+        "{}: chunk {}, {} hasn't been generated",
+        region.path().display(),
+        x,
+        z
+    )
+}
@@ -0,0 +1,469 @@
+use std::{
+    borrow::Borrow,
+    fmt, fs,
+    io::{self, Read, Write},
+    marker::PhantomData,
+    path::{Path, PathBuf},
+};
+
+use flate2::{
+    bufread::{GzDecoder, ZlibDecoder},
+    write::{GzEncoder, ZlibEncoder},
+    Compression,
+};
+use serde::de::DeserializeOwned;
+
+use crate::{
+    block_iter::{BlockFilter, ChunksBlocks},
+    error::McaError,
+    lz4::{Lz4BlockDecoder, Lz4BlockEncoder},
+    nbt_serde,
+    pos::ChunkPos,
+    region::RegionChunk,
+};
+
+// Region files are split up into 4KiB sectors.
+pub(crate) const SECTOR_SIZE: usize = 4096;
+// The location and timestamp tables each take up a single sector.
+pub(crate) const HEADER_SIZE: usize = SECTOR_SIZE * 2;
+// A region holds 32x32 chunks.
+pub(crate) const REGION_WIDTH: usize = 32;
+pub(crate) const CHUNKS_PER_REGION: usize = REGION_WIDTH * REGION_WIDTH;
+
+#[derive(Debug)]
+pub(crate) struct ChunkInfo {
+    // Offset of where the chunk is located in the file.
+    pub(crate) chunk_offset_bytes: usize,
+    // Size of the chunk.
+    pub(crate) size: usize,
+    // Timestamp of the last time the chunk was modified.
+    pub(crate) timestamp: u32,
+}
+
+impl ChunkInfo {
+    // Reads the location and timestamp entries for the chunk at `index` out of the region header.
+    pub(crate) fn from_header(header: &[u8], index: usize) -> Option<Self> {
+        let location = header.get(index * 4..index * 4 + 4)?;
+        let chunk_offset = u32::from_be_bytes([0, location[0], location[1], location[2]]);
+        let size = location[3];
+        // If chunk offset and size are 0 then the chunk hasn't been generated yet.
+        if chunk_offset == 0 || size == 0 {
+            return None;
+        }
+        let timestamp_offset = SECTOR_SIZE + index * 4;
+        let timestamp = header.get(timestamp_offset..timestamp_offset + 4)?;
+        Some(Self {
+            chunk_offset_bytes: (chunk_offset as usize) * SECTOR_SIZE,
+            size: (size as usize) * SECTOR_SIZE,
+            timestamp: u32::from_be_bytes([timestamp[0], timestamp[1], timestamp[2], timestamp[3]]),
+        })
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct ChunkHeader {
+    // Length of the chunk data in bytes, including the compression scheme byte.
+    pub(crate) length: u32,
+    // Raw compression scheme id, see `CompressionScheme`.
+    pub(crate) compression_scheme: u8,
+}
+
+impl From<[u8; 5]> for ChunkHeader {
+    fn from(value: [u8; 5]) -> Self {
+        Self {
+            length: u32::from_be_bytes([value[0], value[1], value[2], value[3]]),
+            compression_scheme: value[4],
+        }
+    }
+}
+
+// Compression scheme id used when the chunk was compressed with a custom, named algorithm.
+pub(crate) const CUSTOM_COMPRESSION: u8 = 127;
+// Set on the compression scheme when the chunk was too big for the region file and is
+// stored in a separate `c.<x>.<z>.mcc` file next to it instead.
+pub(crate) const EXTERNAL_CHUNK_FLAG: u8 = 0x80;
+
+// Parses the region coordinates out of a region file name, `r.<x>.<z>.mca` or `r.<x>.<z>.mcr` for
+// McRegion files from before 1.2. Both use the same container format.
+pub(crate) fn region_coordinates(path: &Path) -> Option<(i32, i32)> {
+    let file_name = path.file_name()?.to_str()?;
+    let mut parts = file_name.split('.');
+    if parts.next()? != "r" {
+        return None;
+    }
+    let x = parts.next()?.parse().ok()?;
+    let z = parts.next()?.parse().ok()?;
+    match (parts.next(), parts.next()) {
+        (Some("mca" | "mcr"), None) => Some((x, z)),
+        _ => None,
+    }
+}
+
+// Path of the `.mcc` file an oversized chunk is stored in, named after the absolute chunk coordinates.
+pub(crate) fn external_chunk_path(
+    region_path: &Path,
+    local_x: usize,
+    local_z: usize,
+) -> Option<PathBuf> {
+    let (region_x, region_z) = region_coordinates(region_path)?;
+    let chunk_x = region_x * REGION_WIDTH as i32 + local_x as i32;
+    let chunk_z = region_z * REGION_WIDTH as i32 + local_z as i32;
+    Some(region_path.with_file_name(format!("c.{}.{}.mcc", chunk_x, chunk_z)))
+}
+
+// Absolute position of a chunk in a region file, regions that aren't named `r.<x>.<z>.mca`
+// are treated as region 0, 0.
+pub(crate) fn chunk_pos(region_path: &Path, local_x: usize, local_z: usize) -> ChunkPos {
+    let (region_x, region_z) = region_coordinates(region_path).unwrap_or((0, 0));
+    ChunkPos::new(
+        region_x * REGION_WIDTH as i32 + local_x as i32,
+        region_z * REGION_WIDTH as i32 + local_z as i32,
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressionScheme {
+    Gzip,
+    Zlib,
+    Uncompressed,
+    Lz4,
+}
+
+impl TryFrom<u8> for CompressionScheme {
+    type Error = UnsupportedCompression;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(CompressionScheme::Gzip),
+            2 => Ok(CompressionScheme::Zlib),
+            3 => Ok(CompressionScheme::Uncompressed),
+            4 => Ok(CompressionScheme::Lz4),
+            _ => Err(UnsupportedCompression::Unknown(value)),
+        }
+    }
+}
+
+impl From<CompressionScheme> for u8 {
+    fn from(value: CompressionScheme) -> Self {
+        match value {
+            CompressionScheme::Gzip => 1,
+            CompressionScheme::Zlib => 2,
+            CompressionScheme::Uncompressed => 3,
+            CompressionScheme::Lz4 => 4,
+        }
+    }
+}
+
+/// A chunk was stored with a compression scheme this crate can't decompress.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UnsupportedCompression {
+    // A compression scheme id that isn't part of the region file format.
+    Unknown(u8),
+    // Scheme 127, the chunk was compressed by a named algorithm added by a server mod.
+    Custom(String),
+}
+
+impl fmt::Display for UnsupportedCompression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnsupportedCompression::Unknown(value) => {
+                write!(f, "Unknown compression scheme {}", value)
+            }
+            UnsupportedCompression::Custom(name) => {
+                write!(f, "Unsupported custom compression algorithm '{}'", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for UnsupportedCompression {}
+
+impl From<UnsupportedCompression> for io::Error {
+    fn from(value: UnsupportedCompression) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, value)
+    }
+}
+
+// Custom compressed chunks start with the algorithm name as a length prefixed (u16) string.
+pub(crate) fn custom_compression_name(bytes: &[u8]) -> String {
+    let length = match bytes.get(0..2) {
+        Some(length) => u16::from_be_bytes([length[0], length[1]]) as usize,
+        None => return String::new(),
+    };
+    bytes
+        .get(2..2 + length)
+        .map(|name| String::from_utf8_lossy(name).into_owned())
+        .unwrap_or_default()
+}
+
+pub(crate) fn decompress_bytes_with_scheme<'a>(
+    bytes: &'a [u8],
+    compression_scheme: CompressionScheme,
+) -> Box<dyn Read + 'a> {
+    match compression_scheme {
+        CompressionScheme::Gzip => Box::new(GzDecoder::new(bytes)),
+        CompressionScheme::Zlib => Box::new(ZlibDecoder::new(bytes)),
+        CompressionScheme::Uncompressed => Box::new(bytes),
+        CompressionScheme::Lz4 => Box::new(Lz4BlockDecoder::new(bytes)),
+    }
+}
+
+pub(crate) fn compress_bytes_with_scheme(
+    bytes: &[u8],
+    compression_scheme: CompressionScheme,
+) -> io::Result<Vec<u8>> {
+    match compression_scheme {
+        CompressionScheme::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(bytes)?;
+            encoder.finish()
+        }
+        CompressionScheme::Zlib => {
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(bytes)?;
+            encoder.finish()
+        }
+        CompressionScheme::Uncompressed => Ok(bytes.to_vec()),
+        CompressionScheme::Lz4 => {
+            let mut encoder = Lz4BlockEncoder::new(Vec::new());
+            encoder.write_all(bytes)?;
+            encoder.finish()
+        }
+    }
+}
+
+/// A region file whose header has been parsed, chunks are only decompressed when they are asked for.
+pub struct RegionFile {
+    path: PathBuf,
+    bytes: Vec<u8>,
+    chunk_infos: Vec<Option<ChunkInfo>>,
+}
+
+impl RegionFile {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, McaError> {
+        let path = path.as_ref().to_path_buf();
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(source) => return Err(McaError::Io { path, source }),
+        };
+        if bytes.len() < HEADER_SIZE {
+            return Err(McaError::TruncatedHeader { path });
+        }
+        // The first 8KiB of the MCA file is the header which contains the location and timestamp tables for each chunk.
+        let chunk_infos = (0..CHUNKS_PER_REGION)
+            .map(|index| ChunkInfo::from_header(&bytes[..HEADER_SIZE], index))
+            .collect();
+        Ok(Self {
+            path,
+            bytes,
+            chunk_infos,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns true if the chunk at the given region-local coordinates has been generated.
+    pub fn has_chunk(&self, local_x: usize, local_z: usize) -> bool {
+        self.chunk_info(local_x, local_z).is_some()
+    }
+
+    /// Timestamp (in seconds since the epoch) of the last time the chunk was saved.
+    pub fn chunk_timestamp(&self, local_x: usize, local_z: usize) -> Option<u32> {
+        self.chunk_info(local_x, local_z)
+            .map(|chunk_info| chunk_info.timestamp)
+    }
+
+    /// Absolute position of the chunk at the given region-local coordinates.
+    pub fn chunk_pos(&self, local_x: usize, local_z: usize) -> ChunkPos {
+        chunk_pos(&self.path, local_x, local_z)
+    }
+
+    /// Region coordinates parsed from the file name, `None` if it isn't named `r.<x>.<z>.mca`.
+    pub fn coordinates(&self) -> Option<(i32, i32)> {
+        region_coordinates(&self.path)
+    }
+
+    /// Number of bytes allocated to the chunk in the region file.
+    pub fn chunk_size(&self, local_x: usize, local_z: usize) -> Option<usize> {
+        self.chunk_info(local_x, local_z)
+            .map(|chunk_info| chunk_info.size)
+    }
+
+    /// Region-local coordinates of every generated chunk in the region.
+    pub fn local_positions(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..CHUNKS_PER_REGION)
+            .filter(|index| self.chunk_infos[*index].is_some())
+            .map(|index| (index % REGION_WIDTH, index / REGION_WIDTH))
+    }
+
+    /// Lazily decodes every generated chunk in the region. A chunk that fails to parse yields
+    /// an error for that chunk only, so the remaining chunks can still be read.
+    pub fn chunks<T>(&self) -> Chunks<&RegionFile, T>
+    where
+        T: DeserializeOwned,
+    {
+        Chunks {
+            region: self,
+            index: 0,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Same as `chunks` but takes ownership of the region file.
+    pub fn into_chunks<T>(self) -> Chunks<RegionFile, T>
+    where
+        T: DeserializeOwned,
+    {
+        Chunks {
+            region: self,
+            index: 0,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// The blocks in the region matching `filter`, chunk by chunk.
+    pub fn blocks(
+        &self,
+        filter: BlockFilter,
+    ) -> ChunksBlocks<impl Iterator<Item = Result<RegionChunk, McaError>> + '_> {
+        ChunksBlocks::new(self.chunks::<RegionChunk>().map(|(_, chunk)| chunk), filter)
+    }
+
+    /// Decompresses and deserializes a single chunk, `None` if the chunk hasn't been generated.
+    pub fn get_chunk<T>(&self, local_x: usize, local_z: usize) -> Result<Option<T>, McaError>
+    where
+        T: DeserializeOwned,
+    {
+        match self.chunk_data(local_x, local_z)? {
+            Some(decompressed) => match nbt_serde::from_bytes(&decompressed) {
+                Ok(chunk) => Ok(Some(chunk)),
+                Err(source) => Err(McaError::Nbt {
+                    path: self.path.clone(),
+                    chunk: self.chunk_pos(local_x, local_z),
+                    source,
+                }),
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the decompressed NBT bytes of a single chunk.
+    pub fn chunk_data(&self, local_x: usize, local_z: usize) -> Result<Option<Vec<u8>>, McaError> {
+        if local_x >= REGION_WIDTH || local_z >= REGION_WIDTH {
+            return Err(McaError::ChunkOutOfRange {
+                path: self.path.clone(),
+                local_x,
+                local_z,
+            });
+        }
+        let chunk_info = match self.chunk_info(local_x, local_z) {
+            Some(chunk_info) => chunk_info,
+            None => return Ok(None),
+        };
+        let chunk = self.chunk_pos(local_x, local_z);
+        let out_of_bounds = || McaError::SectorOutOfBounds {
+            path: self.path.clone(),
+            chunk,
+        };
+        // The chunk can't overlap the header.
+        if chunk_info.chunk_offset_bytes < HEADER_SIZE {
+            return Err(out_of_bounds());
+        }
+        // Read first 5 bytes as chunk header
+        let mut current_offset = chunk_info.chunk_offset_bytes;
+        let header_bytes: [u8; 5] = self
+            .bytes
+            .get(current_offset..current_offset + 5)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(out_of_bounds)?;
+        current_offset += 5;
+        let header = ChunkHeader::from(header_bytes);
+        // The chunk has to fit in the sectors allocated to it.
+        if header.length as usize + 4 > chunk_info.size {
+            return Err(out_of_bounds());
+        }
+        // The length includes the compression scheme byte which was already read.
+        let length = (header.length as usize).saturating_sub(1);
+        let external_bytes;
+        let nbt_bytes = if header.compression_scheme & EXTERNAL_CHUNK_FLAG != 0 {
+            let path = external_chunk_path(&self.path, local_x, local_z).ok_or_else(|| {
+                McaError::UnknownRegionCoordinates {
+                    path: self.path.clone(),
+                    chunk,
+                }
+            })?;
+            external_bytes = fs::read(path).map_err(|source| McaError::ExternalChunk {
+                path: self.path.clone(),
+                chunk,
+                source,
+            })?;
+            &external_bytes[..]
+        } else {
+            self.bytes
+                .get(current_offset..current_offset + length)
+                .ok_or_else(out_of_bounds)?
+        };
+        let bad_compression = |source| McaError::BadCompression {
+            path: self.path.clone(),
+            chunk,
+            source,
+        };
+        let compression_scheme = match header.compression_scheme & !EXTERNAL_CHUNK_FLAG {
+            CUSTOM_COMPRESSION => {
+                let name = custom_compression_name(nbt_bytes);
+                return Err(bad_compression(UnsupportedCompression::Custom(name)));
+            }
+            value => CompressionScheme::try_from(value).map_err(bad_compression)?,
+        };
+        let mut decompressed = Vec::new();
+        let mut reader = decompress_bytes_with_scheme(nbt_bytes, compression_scheme);
+        reader
+            .read_to_end(&mut decompressed)
+            .map_err(|source| McaError::Decompress {
+                path: self.path.clone(),
+                chunk,
+                source,
+            })?;
+        Ok(Some(decompressed))
+    }
+
+    fn chunk_info(&self, local_x: usize, local_z: usize) -> Option<&ChunkInfo> {
+        if local_x >= REGION_WIDTH || local_z >= REGION_WIDTH {
+            return None;
+        }
+        self.chunk_infos[local_x + local_z * REGION_WIDTH].as_ref()
+    }
+}
+
+/// Iterator over the chunks of a region file, created by `RegionFile::chunks` and `RegionFile::into_chunks`.
+pub struct Chunks<R, T> {
+    region: R,
+    index: usize,
+    _phantom: PhantomData<fn() -> T>,
+}
+
+impl<R, T> Iterator for Chunks<R, T>
+where
+    R: Borrow<RegionFile>,
+    T: DeserializeOwned,
+{
+    type Item = (ChunkPos, Result<T, McaError>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.index < CHUNKS_PER_REGION {
+            let (local_x, local_z) = (self.index % REGION_WIDTH, self.index / REGION_WIDTH);
+            self.index += 1;
+            let region = self.region.borrow();
+            let chunk = match region.get_chunk(local_x, local_z) {
+                Ok(Some(chunk)) => Ok(chunk),
+                // The chunk hasn't been generated.
+                Ok(None) => continue,
+                Err(err) => Err(err),
+            };
+            return Some((region.chunk_pos(local_x, local_z), chunk));
+        }
+        None
+    }
+}
@@ -0,0 +1,709 @@
+// Serde support for NBT that goes through the `nbt::Value` tree. nbt's own (de)serializer can't
+// tell a list of ints from an int array, and serde buffers flattened fields in a form where
+// booleans stored as bytes no longer deserialize. Reading the raw tree first and converting it
+// here keeps every tag's type, so tags that aren't modelled are written back exactly as read.
+
+use std::{collections::HashMap, io::Read, iter};
+
+use nbt::Value;
+use serde::{
+    de::{
+        self,
+        value::{MapDeserializer, SeqDeserializer},
+        DeserializeOwned, DeserializeSeed, EnumAccess, IntoDeserializer, VariantAccess, Visitor,
+    },
+    forward_to_deserialize_any,
+    ser::{self, Impossible},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+const COMPOUND_ID: u8 = 0x0a;
+
+// A struct field with this name collects every tag the struct has no other field for, and its
+// tags are written next to the struct's other fields. It must be a compound, see `compound`.
+pub(crate) const EXTRA: &str = "$extra";
+
+// Enum name `Tag` asks for, telling the deserializer to hand over the tag with its type id.
+const TAG: &str = "$nbt::Tag";
+
+// Reads an uncompressed NBT file, the root compound's name is ignored.
+pub(crate) fn from_bytes<T: DeserializeOwned>(mut bytes: &[u8]) -> nbt::Result<T> {
+    let mut header = [0; 3];
+    bytes.read_exact(&mut header)?;
+    if header[0] != COMPOUND_ID {
+        return Err(nbt::Error::NoRootCompound);
+    }
+    let name_len = u16::from_be_bytes([header[1], header[2]]) as usize;
+    let mut payload = bytes
+        .get(name_len..)
+        .ok_or(nbt::Error::IncompleteNbtValue)?;
+    from_value(Value::from_reader(COMPOUND_ID, &mut payload)?)
+}
+
+// Writes `value` as an uncompressed NBT file with an unnamed root compound.
+pub(crate) fn to_bytes<T: Serialize + ?Sized>(value: &T) -> nbt::Result<Vec<u8>> {
+    let value = to_value(value)?;
+    if !matches!(value, Value::Compound(_)) {
+        return Err(nbt::Error::NoRootCompound);
+    }
+    let mut bytes = vec![COMPOUND_ID, 0, 0];
+    value.to_writer(&mut bytes)?;
+    Ok(bytes)
+}
+
+pub(crate) fn from_value<T: DeserializeOwned>(value: Value) -> nbt::Result<T> {
+    T::deserialize(ValueDeserializer(value))
+}
+
+pub(crate) fn to_value<T: Serialize + ?Sized>(value: &T) -> nbt::Result<Value> {
+    value
+        .serialize(ValueSerializer)?
+        .ok_or(nbt::Error::UnrepresentableType("none"))
+}
+
+// Serializes a struct or map into the entries of a compound.
+pub(crate) fn to_compound<T: Serialize + ?Sized>(value: &T) -> nbt::Result<HashMap<String, Value>> {
+    match to_value(value)? {
+        Value::Compound(entries) => Ok(entries),
+        _ => Err(nbt::Error::NoRootCompound),
+    }
+}
+
+// `#[serde(with = "nbt_serde::value")]` for `nbt::Value` fields, whose own serde implementation
+// guesses the type of arrays and lists.
+pub(crate) mod value {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(
+        value: &Value,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        TagRef(value).serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Value, D::Error> {
+        Tag::deserialize(deserializer).map(|tag| tag.0)
+    }
+}
+
+// `#[serde(default, with = "nbt_serde::option")]` for optional `nbt::Value` fields.
+pub(crate) mod option {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(
+        value: &Option<Value>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        value.as_ref().map(TagRef).serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Value>, D::Error> {
+        Option::<Tag>::deserialize(deserializer).map(|tag| tag.map(|tag| tag.0))
+    }
+}
+
+// `#[serde(rename = "$extra", default, with = "nbt_serde::compound")]` for the field holding the
+// tags a struct doesn't model.
+pub(crate) mod compound {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(
+        compound: &HashMap<String, Value>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(compound.iter().map(|(name, value)| (name, TagRef(value))))
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<String, Value>, D::Error> {
+        let compound = HashMap::<String, Tag>::deserialize(deserializer)?;
+        Ok(compound
+            .into_iter()
+            .map(|(name, tag)| (name, tag.0))
+            .collect())
+    }
+}
+
+// A tag deserialized together with its type id, which only `ValueDeserializer` provides.
+struct Tag(Value);
+
+impl<'de> Deserialize<'de> for Tag {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_enum(TAG, &[], TagVisitor).map(Tag)
+    }
+}
+
+struct TagVisitor;
+
+impl<'de> Visitor<'de> for TagVisitor {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("an NBT tag")
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Value, A::Error> {
+        let (id, tag): (u8, _) = data.variant()?;
+        Ok(match id {
+            0x01 => Value::Byte(tag.newtype_variant()?),
+            0x02 => Value::Short(tag.newtype_variant()?),
+            0x03 => Value::Int(tag.newtype_variant()?),
+            0x04 => Value::Long(tag.newtype_variant()?),
+            0x05 => Value::Float(tag.newtype_variant()?),
+            0x06 => Value::Double(tag.newtype_variant()?),
+            0x07 => Value::ByteArray(tag.newtype_variant()?),
+            0x08 => Value::String(tag.newtype_variant()?),
+            0x09 => {
+                let list: Vec<Tag> = tag.newtype_variant()?;
+                Value::List(list.into_iter().map(|tag| tag.0).collect())
+            }
+            0x0a => {
+                let compound: HashMap<String, Tag> = tag.newtype_variant()?;
+                Value::Compound(
+                    compound
+                        .into_iter()
+                        .map(|(name, tag)| (name, tag.0))
+                        .collect(),
+                )
+            }
+            0x0b => Value::IntArray(tag.newtype_variant()?),
+            0x0c => Value::LongArray(tag.newtype_variant()?),
+            id => return Err(de::Error::custom(format!("invalid NBT tag id {id}"))),
+        })
+    }
+}
+
+// Serializes a tag with its exact type, arrays use the array helpers the serializers know.
+struct TagRef<'a>(&'a Value);
+
+impl Serialize for TagRef<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0 {
+            Value::Byte(value) => serializer.serialize_i8(*value),
+            Value::Short(value) => serializer.serialize_i16(*value),
+            Value::Int(value) => serializer.serialize_i32(*value),
+            Value::Long(value) => serializer.serialize_i64(*value),
+            Value::Float(value) => serializer.serialize_f32(*value),
+            Value::Double(value) => serializer.serialize_f64(*value),
+            Value::ByteArray(values) => nbt::i8_array(values, serializer),
+            Value::String(value) => serializer.serialize_str(value),
+            Value::List(values) => serializer.collect_seq(values.iter().map(TagRef)),
+            Value::Compound(values) => {
+                serializer.collect_map(values.iter().map(|(name, value)| (name, TagRef(value))))
+            }
+            Value::IntArray(values) => nbt::i32_array(values, serializer),
+            Value::LongArray(values) => nbt::i64_array(values, serializer),
+        }
+    }
+}
+
+struct ValueDeserializer(Value);
+
+impl<'de> IntoDeserializer<'de, nbt::Error> for ValueDeserializer {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+fn seq<'de, I, V>(values: I, visitor: V) -> nbt::Result<V::Value>
+where
+    I: Iterator<Item = Value>,
+    V: Visitor<'de>,
+{
+    SeqDeserializer::new(values.map(ValueDeserializer)).deserialize_any(visitor)
+}
+
+fn map<'de, I, V>(entries: I, visitor: V) -> nbt::Result<V::Value>
+where
+    I: Iterator<Item = (String, Value)>,
+    V: Visitor<'de>,
+{
+    MapDeserializer::new(entries.map(|(name, value)| (name, ValueDeserializer(value))))
+        .deserialize_any(visitor)
+}
+
+impl<'de> Deserializer<'de> for ValueDeserializer {
+    type Error = nbt::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> nbt::Result<V::Value> {
+        match self.0 {
+            Value::Byte(value) => visitor.visit_i8(value),
+            Value::Short(value) => visitor.visit_i16(value),
+            Value::Int(value) => visitor.visit_i32(value),
+            Value::Long(value) => visitor.visit_i64(value),
+            Value::Float(value) => visitor.visit_f32(value),
+            Value::Double(value) => visitor.visit_f64(value),
+            Value::ByteArray(values) => seq(values.into_iter().map(Value::Byte), visitor),
+            Value::String(value) => visitor.visit_string(value),
+            Value::List(values) => seq(values.into_iter(), visitor),
+            Value::Compound(entries) => map(entries.into_iter(), visitor),
+            Value::IntArray(values) => seq(values.into_iter().map(Value::Int), visitor),
+            Value::LongArray(values) => seq(values.into_iter().map(Value::Long), visitor),
+        }
+    }
+
+    // Booleans are stored as bytes.
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> nbt::Result<V::Value> {
+        match self.0 {
+            Value::Byte(value) => visitor.visit_bool(value != 0),
+            value => ValueDeserializer(value).deserialize_any(visitor),
+        }
+    }
+
+    // Missing tags are `None`, a tag that is present is always `Some`.
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> nbt::Result<V::Value> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> nbt::Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> nbt::Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> nbt::Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> nbt::Result<V::Value> {
+        match self.0 {
+            Value::Compound(entries) if fields.contains(&EXTRA) => {
+                let (known, extra): (HashMap<_, _>, HashMap<_, _>) = entries
+                    .into_iter()
+                    .partition(|(name, _)| fields.contains(&name.as_str()));
+                let extra = (EXTRA.to_string(), Value::Compound(extra));
+                map(known.into_iter().chain(iter::once(extra)), visitor)
+            }
+            value => ValueDeserializer(value).deserialize_any(visitor),
+        }
+    }
+
+    // Unit variants are stored by name, `Tag` asks for the tag and its type id.
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> nbt::Result<V::Value> {
+        match self.0 {
+            value if name == TAG => visitor.visit_enum(TagAccess(value)),
+            Value::String(variant) => visitor.visit_enum(variant.into_deserializer()),
+            value => ValueDeserializer(value).deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> nbt::Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf seq tuple
+        tuple_struct map identifier
+    }
+}
+
+struct TagAccess(Value);
+
+impl<'de> EnumAccess<'de> for TagAccess {
+    type Error = nbt::Error;
+    type Variant = ValueDeserializer;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> nbt::Result<(V::Value, ValueDeserializer)> {
+        let id = seed.deserialize(IntoDeserializer::<nbt::Error>::into_deserializer(
+            self.0.id(),
+        ))?;
+        Ok((id, ValueDeserializer(self.0)))
+    }
+}
+
+impl<'de> VariantAccess<'de> for ValueDeserializer {
+    type Error = nbt::Error;
+
+    fn unit_variant(self) -> nbt::Result<()> {
+        Err(de::Error::custom("expected an NBT tag"))
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> nbt::Result<T::Value> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> nbt::Result<V::Value> {
+        self.deserialize_any(visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> nbt::Result<V::Value> {
+        self.deserialize_any(visitor)
+    }
+}
+
+// Serializes into a tag, `None` for values that leave the tag out such as `Option::None`.
+struct ValueSerializer;
+
+impl Serializer for ValueSerializer {
+    type Ok = Option<Value>;
+    type Error = nbt::Error;
+    type SerializeSeq = ListSerializer;
+    type SerializeTuple = ListSerializer;
+    type SerializeTupleStruct = ListSerializer;
+    type SerializeTupleVariant = Impossible<Option<Value>, nbt::Error>;
+    type SerializeMap = CompoundSerializer;
+    type SerializeStruct = CompoundSerializer;
+    type SerializeStructVariant = Impossible<Option<Value>, nbt::Error>;
+
+    fn serialize_bool(self, value: bool) -> nbt::Result<Option<Value>> {
+        Ok(Some(Value::Byte(value as i8)))
+    }
+
+    fn serialize_i8(self, value: i8) -> nbt::Result<Option<Value>> {
+        Ok(Some(Value::Byte(value)))
+    }
+
+    fn serialize_i16(self, value: i16) -> nbt::Result<Option<Value>> {
+        Ok(Some(Value::Short(value)))
+    }
+
+    fn serialize_i32(self, value: i32) -> nbt::Result<Option<Value>> {
+        Ok(Some(Value::Int(value)))
+    }
+
+    fn serialize_i64(self, value: i64) -> nbt::Result<Option<Value>> {
+        Ok(Some(Value::Long(value)))
+    }
+
+    // NBT has no unsigned types, the bits are stored in the signed type of the same width.
+    fn serialize_u8(self, value: u8) -> nbt::Result<Option<Value>> {
+        Ok(Some(Value::Byte(value as i8)))
+    }
+
+    fn serialize_u16(self, value: u16) -> nbt::Result<Option<Value>> {
+        Ok(Some(Value::Short(value as i16)))
+    }
+
+    fn serialize_u32(self, value: u32) -> nbt::Result<Option<Value>> {
+        Ok(Some(Value::Int(value as i32)))
+    }
+
+    fn serialize_u64(self, value: u64) -> nbt::Result<Option<Value>> {
+        Ok(Some(Value::Long(value as i64)))
+    }
+
+    fn serialize_f32(self, value: f32) -> nbt::Result<Option<Value>> {
+        Ok(Some(Value::Float(value)))
+    }
+
+    fn serialize_f64(self, value: f64) -> nbt::Result<Option<Value>> {
+        Ok(Some(Value::Double(value)))
+    }
+
+    fn serialize_char(self, value: char) -> nbt::Result<Option<Value>> {
+        Ok(Some(Value::String(value.to_string())))
+    }
+
+    fn serialize_str(self, value: &str) -> nbt::Result<Option<Value>> {
+        Ok(Some(Value::String(value.to_string())))
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> nbt::Result<Option<Value>> {
+        Ok(Some(Value::ByteArray(
+            value.iter().map(|byte| *byte as i8).collect(),
+        )))
+    }
+
+    fn serialize_none(self) -> nbt::Result<Option<Value>> {
+        Ok(None)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> nbt::Result<Option<Value>> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> nbt::Result<Option<Value>> {
+        Ok(None)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> nbt::Result<Option<Value>> {
+        Ok(Some(Value::Compound(HashMap::new())))
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> nbt::Result<Option<Value>> {
+        Ok(Some(Value::String(variant.to_string())))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> nbt::Result<Option<Value>> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> nbt::Result<Option<Value>> {
+        Err(nbt::Error::UnrepresentableType("newtype variant"))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> nbt::Result<ListSerializer> {
+        Ok(ListSerializer::new(ListKind::List, len))
+    }
+
+    fn serialize_tuple(self, len: usize) -> nbt::Result<ListSerializer> {
+        self.serialize_seq(Some(len))
+    }
+
+    // `nbt::i8_array` and friends mark arrays with these names.
+    fn serialize_tuple_struct(self, name: &'static str, len: usize) -> nbt::Result<ListSerializer> {
+        let kind = match name {
+            "__hematite_nbt_i8_array__" => ListKind::ByteArray,
+            "__hematite_nbt_i32_array__" => ListKind::IntArray,
+            "__hematite_nbt_i64_array__" => ListKind::LongArray,
+            _ => ListKind::List,
+        };
+        Ok(ListSerializer::new(kind, Some(len)))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> nbt::Result<Self::SerializeTupleVariant> {
+        Err(nbt::Error::UnrepresentableType("tuple variant"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> nbt::Result<CompoundSerializer> {
+        Ok(CompoundSerializer::default())
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> nbt::Result<CompoundSerializer> {
+        Ok(CompoundSerializer::default())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> nbt::Result<Self::SerializeStructVariant> {
+        Err(nbt::Error::UnrepresentableType("struct variant"))
+    }
+}
+
+enum ListKind {
+    List,
+    ByteArray,
+    IntArray,
+    LongArray,
+}
+
+struct ListSerializer {
+    kind: ListKind,
+    values: Vec<Value>,
+}
+
+impl ListSerializer {
+    fn new(kind: ListKind, len: Option<usize>) -> Self {
+        Self {
+            kind,
+            values: Vec::with_capacity(len.unwrap_or_default()),
+        }
+    }
+}
+
+impl ser::SerializeSeq for ListSerializer {
+    type Ok = Option<Value>;
+    type Error = nbt::Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> nbt::Result<()> {
+        self.values.push(to_value(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> nbt::Result<Option<Value>> {
+        let values = self.values;
+        let value = match self.kind {
+            ListKind::List => Value::List(values),
+            ListKind::ByteArray => Value::ByteArray(array(values, |value| match value {
+                Value::Byte(value) => Some(value),
+                _ => None,
+            })?),
+            ListKind::IntArray => Value::IntArray(array(values, |value| match value {
+                Value::Int(value) => Some(value),
+                _ => None,
+            })?),
+            ListKind::LongArray => Value::LongArray(array(values, |value| match value {
+                Value::Long(value) => Some(value),
+                _ => None,
+            })?),
+        };
+        Ok(Some(value))
+    }
+}
+
+fn array<T>(values: Vec<Value>, element: fn(Value) -> Option<T>) -> nbt::Result<Vec<T>> {
+    values
+        .into_iter()
+        .map(|value| element(value).ok_or(nbt::Error::HeterogeneousList))
+        .collect()
+}
+
+impl ser::SerializeTuple for ListSerializer {
+    type Ok = Option<Value>;
+    type Error = nbt::Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> nbt::Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> nbt::Result<Option<Value>> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for ListSerializer {
+    type Ok = Option<Value>;
+    type Error = nbt::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> nbt::Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> nbt::Result<Option<Value>> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+#[derive(Default)]
+struct CompoundSerializer {
+    entries: HashMap<String, Value>,
+    name: Option<String>,
+}
+
+impl ser::SerializeMap for CompoundSerializer {
+    type Ok = Option<Value>;
+    type Error = nbt::Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> nbt::Result<()> {
+        match key.serialize(ValueSerializer)? {
+            Some(Value::String(name)) => {
+                self.name = Some(name);
+                Ok(())
+            }
+            _ => Err(nbt::Error::NonStringMapKey),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> nbt::Result<()> {
+        let name = self.name.take().ok_or(nbt::Error::NonStringMapKey)?;
+        if let Some(value) = value.serialize(ValueSerializer)? {
+            self.entries.insert(name, value);
+        }
+        Ok(())
+    }
+
+    fn end(self) -> nbt::Result<Option<Value>> {
+        Ok(Some(Value::Compound(self.entries)))
+    }
+}
+
+impl ser::SerializeStruct for CompoundSerializer {
+    type Ok = Option<Value>;
+    type Error = nbt::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        name: &'static str,
+        value: &T,
+    ) -> nbt::Result<()> {
+        match value.serialize(ValueSerializer)? {
+            // The modelled fields win if a tag somehow ended up in both.
+            Some(Value::Compound(extra)) if name == EXTRA => {
+                for (name, value) in extra {
+                    self.entries.entry(name).or_insert(value);
+                }
+            }
+            Some(value) => {
+                self.entries.insert(name.to_string(), value);
+            }
+            None => {}
+        }
+        Ok(())
+    }
+
+    fn end(self) -> nbt::Result<Option<Value>> {
+        Ok(Some(Value::Compound(self.entries)))
+    }
+}
+
+#[test]
+fn test_tags_round_trip() {
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct Chest {
+        id: String,
+        open: bool,
+        #[serde(rename = "$extra", default, with = "compound")]
+        extra: HashMap<String, Value>,
+    }
+
+    let compound: HashMap<String, Value> = [
+        ("id", Value::String("minecraft:chest".to_string())),
+        ("open", Value::Byte(1)),
+        ("UUID", Value::IntArray(vec![1, 2, 3, 4])),
+        ("Bytes", Value::List(vec![Value::Byte(1), Value::Byte(2)])),
+        ("Heights", Value::LongArray(vec![-1, 7])),
+    ]
+    .into_iter()
+    .map(|(name, value)| (name.to_string(), value))
+    .collect();
+    let bytes = to_bytes(&TagRef(&Value::Compound(compound))).unwrap();
+
+    let chest: Chest = from_bytes(&bytes).unwrap();
+    assert!(chest.open);
+    assert_eq!(chest.extra.len(), 3);
+    assert_eq!(chest.extra["UUID"], Value::IntArray(vec![1, 2, 3, 4]));
+    let written = to_bytes(&chest).unwrap();
+    assert_eq!(
+        nbt::Blob::from_reader(&mut &written[..]).unwrap(),
+        nbt::Blob::from_reader(&mut &bytes[..]).unwrap()
+    );
+}
@@ -0,0 +1,141 @@
+// Fixed width entries packed into longs, as used by block states, biomes and heightmaps.
+// Since 1.16 an entry never spans two longs, the leftover high bits of each long are padding.
+
+/// Bits needed to index a palette of `palette_len` entries. A single entry palette needs no
+/// bits at all, in which case the section stores no data array.
+pub(crate) fn bits_per_entry(palette_len: usize, min_bits: u32) -> u32 {
+    if palette_len <= 1 {
+        return 0;
+    }
+    let bits = usize::BITS - (palette_len - 1).leading_zeros();
+    bits.max(min_bits)
+}
+
+/// Number of longs needed to hold `len` entries of `bits` each.
+pub(crate) fn packed_len(len: usize, bits: u32) -> usize {
+    if bits == 0 {
+        return 0;
+    }
+    let per_long = (64 / bits) as usize;
+    len.div_ceil(per_long)
+}
+
+/// Reads the entry at `index`. Entries past the end of a short (corrupt) array read as 0.
+pub(crate) fn get(data: &[i64], bits: u32, index: usize) -> u16 {
+    if bits == 0 {
+        return 0;
+    }
+    let per_long = (64 / bits) as usize;
+    let shift = (index % per_long) as u32 * bits;
+    data.get(index / per_long)
+        .map(|long| ((*long as u64 >> shift) & mask(bits)) as u16)
+        .unwrap_or(0)
+}
+
+/// Overwrites the entry at `index`, `data` must already be long enough to hold it.
+pub(crate) fn set(data: &mut [i64], bits: u32, index: usize, value: u16) {
+    if bits == 0 {
+        return;
+    }
+    let per_long = (64 / bits) as usize;
+    let shift = (index % per_long) as u32 * bits;
+    let long = &mut data[index / per_long];
+    let cleared = *long as u64 & !(mask(bits) << shift);
+    *long = (cleared | ((value as u64 & mask(bits)) << shift)) as i64;
+}
+
+/// Reads every entry into `values`.
+pub(crate) fn unpack(data: &[i64], bits: u32, values: &mut [u16]) {
+    if bits == 0 {
+        values.fill(0);
+        return;
+    }
+    let per_long = (64 / bits) as usize;
+    for (chunk, long) in values.chunks_mut(per_long).zip(data.iter()) {
+        let long = *long as u64;
+        for (i, value) in chunk.iter_mut().enumerate() {
+            *value = ((long >> (i as u32 * bits)) & mask(bits)) as u16;
+        }
+    }
+    // A data array that is too short leaves the remaining entries at 0.
+    let unpacked = data.len() * per_long;
+    if unpacked < values.len() {
+        values[unpacked..].fill(0);
+    }
+}
+
+/// Packs `values` into longs of `bits` wide entries.
+pub(crate) fn pack(values: &[u16], bits: u32) -> Vec<i64> {
+    let mut data = vec![0i64; packed_len(values.len(), bits)];
+    if bits == 0 {
+        return data;
+    }
+    let per_long = (64 / bits) as usize;
+    for (long, chunk) in data.iter_mut().zip(values.chunks(per_long)) {
+        let mut packed = 0u64;
+        for (i, value) in chunk.iter().enumerate() {
+            packed |= (*value as u64 & mask(bits)) << (i as u32 * bits);
+        }
+        *long = packed as i64;
+    }
+    data
+}
+
+/// Reads every entry of the layout used before 1.16 (data version 2529), where entries are
+/// packed back to back and may span two longs.
+pub(crate) fn unpack_spanning(data: &[i64], bits: u32, values: &mut [u16]) {
+    if bits == 0 {
+        values.fill(0);
+        return;
+    }
+    for (i, value) in values.iter_mut().enumerate() {
+        let bit = i * bits as usize;
+        let (long, shift) = (bit / 64, (bit % 64) as u32);
+        let mut packed = data.get(long).map_or(0, |first| *first as u64 >> shift);
+        if shift + bits > 64 {
+            packed |= data
+                .get(long + 1)
+                .map_or(0, |next| (*next as u64) << (64 - shift));
+        }
+        *value = (packed & mask(bits)) as u16;
+    }
+}
+
+fn mask(bits: u32) -> u64 {
+    (1u64 << bits) - 1
+}
+
+#[test]
+fn test_pack_round_trip() {
+    assert_eq!(bits_per_entry(1, 4), 0);
+    assert_eq!(bits_per_entry(2, 4), 4);
+    assert_eq!(bits_per_entry(17, 4), 5);
+    assert_eq!(bits_per_entry(3, 1), 2);
+
+    // 5 bits leaves 4 bits of padding per long, so 4096 entries need 342 longs.
+    let values: Vec<u16> = (0..4096u16).map(|i| i % 17).collect();
+    let data = pack(&values, 5);
+    assert_eq!(data.len(), 342);
+    let mut unpacked = vec![0u16; 4096];
+    unpack(&data, 5, &mut unpacked);
+    assert_eq!(unpacked, values);
+    assert_eq!(get(&data, 5, 4095), values[4095]);
+}
+
+#[test]
+fn test_unpack_spanning() {
+    // Entries 12 and 13 of a 5 bit array straddle the first two longs.
+    let values: Vec<u16> = (0..64u16).map(|i| (i * 7) % 32).collect();
+    let mut data = vec![0u64; 5];
+    for (i, value) in values.iter().enumerate() {
+        let bit = i * 5;
+        data[bit / 64] |= (*value as u64) << (bit % 64);
+        if bit % 64 + 5 > 64 {
+            data[bit / 64 + 1] |= (*value as u64) >> (64 - bit % 64);
+        }
+    }
+    let data: Vec<i64> = data.into_iter().map(|long| long as i64).collect();
+    let mut unpacked = vec![0u16; 64];
+    unpack_spanning(&data, 5, &mut unpacked);
+    assert_eq!(unpacked, values);
+}
@@ -0,0 +1,396 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    dat::{read_gzip_nbt, write_gzip_nbt},
+    entities::{Attribute, Brain, Entity, Item, PotionEffect},
+    error::McaError,
+    nbt_serde,
+    uuid::Uuid,
+    vec::Vec3I,
+};
+
+/// Contents of a `playerdata/<uuid>.dat` file.
+#[derive(Debug)]
+pub struct PlayerData {
+    // Position, motion, UUID and the other tags every entity has.
+    entity: Entity,
+    tags: PlayerTags,
+}
+
+// The entity and player tags live in the same compound, so both halves are read from the whole
+// compound. Each half keeps the tags it doesn't model, which includes the other half's tags.
+impl<'de> Deserialize<'de> for PlayerData {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let compound = nbt::Value::Compound(nbt_serde::compound::deserialize(deserializer)?);
+        let mut entity: Entity =
+            nbt_serde::from_value(compound.clone()).map_err(de::Error::custom)?;
+        let mut tags: PlayerTags = nbt_serde::from_value(compound).map_err(de::Error::custom)?;
+        // Only tags neither half models are in both catch-alls, keep them in one of them so
+        // they're written back exactly once.
+        let tags_extra = std::mem::take(&mut tags.extra);
+        entity.retain_extra(|name| tags_extra.contains_key(name));
+        Ok(Self { entity, tags })
+    }
+}
+
+impl Serialize for PlayerData {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut compound = nbt_serde::to_compound(&self.entity).map_err(ser::Error::custom)?;
+        compound.extend(nbt_serde::to_compound(&self.tags).map_err(ser::Error::custom)?);
+        nbt_serde::compound::serialize(&compound, serializer)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct PlayerTags {
+    data_version: i32,
+    // Tags shared with mobs.
+    health: f32,
+    absorption_amount: f32,
+    attributes: Vec<Attribute>,
+    #[serde(rename = "active_effects")]
+    active_effects: Option<Vec<PotionEffect>>,
+    #[serde(rename = "Brain")]
+    brain: Option<Brain>,
+    death_time: i16,
+    hurt_time: i16,
+    hurt_by_timestamp: i32,
+    fall_flying: bool,
+    sleep_timer: i16,
+    // Player tags.
+    inventory: Vec<Item>,
+    ender_items: Vec<Item>,
+    selected_item_slot: i32,
+    xp_level: i32,
+    // Progress towards the next level, between 0 and 1.
+    #[serde(rename = "XpP")]
+    xp_progress: f32,
+    xp_total: i32,
+    xp_seed: i32,
+    score: i32,
+    #[serde(rename = "foodLevel")]
+    food_level: i32,
+    #[serde(rename = "foodSaturationLevel")]
+    food_saturation_level: f32,
+    #[serde(rename = "foodExhaustionLevel")]
+    food_exhaustion_level: f32,
+    #[serde(rename = "foodTickTimer")]
+    food_tick_timer: i32,
+    #[serde(rename = "abilities")]
+    abilities: Abilities,
+    #[serde(rename = "playerGameType")]
+    game_type: i32,
+    #[serde(rename = "previousPlayerGameType")]
+    previous_game_type: Option<i32>,
+    dimension: String,
+    // The spawn point is only set once the player sleeps in a bed or uses a respawn anchor.
+    spawn_x: Option<i32>,
+    spawn_y: Option<i32>,
+    spawn_z: Option<i32>,
+    spawn_angle: Option<f32>,
+    spawn_dimension: Option<String>,
+    spawn_forced: Option<bool>,
+    #[serde(rename = "recipeBook")]
+    recipe_book: RecipeBook,
+    #[serde(rename = "seenCredits")]
+    seen_credits: bool,
+    last_death_location: Option<DeathLocation>,
+    // Not modelled yet, kept as is so writing the player back doesn't lose them.
+    #[serde(default, with = "nbt_serde::option")]
+    root_vehicle: Option<nbt::Value>,
+    #[serde(default, with = "nbt_serde::option")]
+    shoulder_entity_left: Option<nbt::Value>,
+    #[serde(default, with = "nbt_serde::option")]
+    shoulder_entity_right: Option<nbt::Value>,
+    #[serde(rename = "enteredNetherPosition", default, with = "nbt_serde::option")]
+    entered_nether_position: Option<nbt::Value>,
+    #[serde(rename = "warden_spawn_tracker", default, with = "nbt_serde::option")]
+    warden_spawn_tracker: Option<nbt::Value>,
+    #[serde(rename = "$extra", default, with = "nbt_serde::compound")]
+    extra: HashMap<String, nbt::Value>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Abilities {
+    flying: bool,
+    fly_speed: f32,
+    // Creative mode instant breaking.
+    instabuild: bool,
+    invulnerable: bool,
+    may_build: bool,
+    mayfly: bool,
+    walk_speed: f32,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecipeBook {
+    recipes: Vec<String>,
+    // Unlocked recipes that still show the "new recipe" highlight.
+    to_be_displayed: Vec<String>,
+    is_filtering_craftable: bool,
+    is_gui_open: bool,
+    is_furnace_filtering_craftable: bool,
+    is_furnace_gui_open: bool,
+    is_blasting_furnace_filtering_craftable: bool,
+    is_blasting_furnace_gui_open: bool,
+    is_smoker_filtering_craftable: bool,
+    is_smoker_gui_open: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DeathLocation {
+    dimension: String,
+    #[serde(serialize_with = "nbt::i32_array")]
+    pos: [i32; 3],
+}
+
+impl PlayerData {
+    /// Reads a gzip compressed player `.dat` file.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self, McaError> {
+        read_gzip_nbt(path.as_ref())
+    }
+
+    /// Writes the player back out as a gzip compressed `.dat` file.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), McaError> {
+        write_gzip_nbt(path.as_ref(), self)
+    }
+
+    pub fn entity(&self) -> &Entity {
+        &self.entity
+    }
+
+    pub fn uuid(&self) -> &Uuid {
+        self.entity.uuid()
+    }
+
+    pub fn data_version(&self) -> i32 {
+        self.tags.data_version
+    }
+
+    pub fn health(&self) -> f32 {
+        self.tags.health
+    }
+
+    pub fn attributes(&self) -> &[Attribute] {
+        &self.tags.attributes
+    }
+
+    pub fn active_effects(&self) -> &[PotionEffect] {
+        self.tags.active_effects.as_deref().unwrap_or_default()
+    }
+
+    pub fn inventory(&self) -> &[Item] {
+        &self.tags.inventory
+    }
+
+    pub fn inventory_mut(&mut self) -> &mut Vec<Item> {
+        &mut self.tags.inventory
+    }
+
+    pub fn ender_items(&self) -> &[Item] {
+        &self.tags.ender_items
+    }
+
+    pub fn ender_items_mut(&mut self) -> &mut Vec<Item> {
+        &mut self.tags.ender_items
+    }
+
+    /// Hotbar slot (0-8) the player is holding.
+    pub fn selected_item_slot(&self) -> i32 {
+        self.tags.selected_item_slot
+    }
+
+    pub fn xp_level(&self) -> i32 {
+        self.tags.xp_level
+    }
+
+    pub fn xp_progress(&self) -> f32 {
+        self.tags.xp_progress
+    }
+
+    pub fn xp_total(&self) -> i32 {
+        self.tags.xp_total
+    }
+
+    pub fn set_xp(&mut self, level: i32, progress: f32, total: i32) {
+        self.tags.xp_level = level;
+        self.tags.xp_progress = progress;
+        self.tags.xp_total = total;
+    }
+
+    pub fn food_level(&self) -> i32 {
+        self.tags.food_level
+    }
+
+    pub fn food_saturation_level(&self) -> f32 {
+        self.tags.food_saturation_level
+    }
+
+    pub fn set_food(&mut self, level: i32, saturation_level: f32) {
+        self.tags.food_level = level;
+        self.tags.food_saturation_level = saturation_level;
+    }
+
+    pub fn abilities(&self) -> &Abilities {
+        &self.tags.abilities
+    }
+
+    /// Dimension the player is currently in.
+    pub fn dimension(&self) -> &str {
+        &self.tags.dimension
+    }
+
+    /// Bed or respawn anchor position and its dimension, `None` if the player hasn't set one.
+    pub fn spawn_point(&self) -> Option<(Vec3I, &str)> {
+        let pos = Vec3I::new(self.tags.spawn_x?, self.tags.spawn_y?, self.tags.spawn_z?);
+        let dimension = self
+            .tags
+            .spawn_dimension
+            .as_deref()
+            .unwrap_or("minecraft:overworld");
+        Some((pos, dimension))
+    }
+
+    pub fn recipe_book(&self) -> &RecipeBook {
+        &self.tags.recipe_book
+    }
+}
+
+impl Abilities {
+    pub fn is_flying(&self) -> bool {
+        self.flying
+    }
+
+    pub fn may_fly(&self) -> bool {
+        self.mayfly
+    }
+
+    pub fn may_build(&self) -> bool {
+        self.may_build
+    }
+
+    pub fn is_invulnerable(&self) -> bool {
+        self.invulnerable
+    }
+
+    pub fn fly_speed(&self) -> f32 {
+        self.fly_speed
+    }
+
+    pub fn walk_speed(&self) -> f32 {
+        self.walk_speed
+    }
+}
+
+impl RecipeBook {
+    pub fn recipes(&self) -> &[String] {
+        &self.recipes
+    }
+}
+
+/// The `playerdata/` folder of a world, which stores a `<uuid>.dat` file per player.
+#[derive(Debug, Clone)]
+pub struct PlayerDataStore {
+    path: PathBuf,
+}
+
+impl PlayerDataStore {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn player_path(&self, uuid: &Uuid) -> PathBuf {
+        self.path.join(format!("{uuid}.dat"))
+    }
+
+    /// UUIDs of every player with a `.dat` file, backups such as `.dat_old` are skipped.
+    pub fn players(&self) -> Result<Vec<Uuid>, McaError> {
+        let io_error = |source| McaError::Io {
+            path: self.path.clone(),
+            source,
+        };
+        let entries = match fs::read_dir(&self.path) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(source) => return Err(io_error(source)),
+        };
+        let mut players = Vec::new();
+        for entry in entries {
+            let path = entry.map_err(io_error)?.path();
+            if path.extension().and_then(|extension| extension.to_str()) != Some("dat") {
+                continue;
+            }
+            if let Some(uuid) = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(Uuid::parse)
+            {
+                players.push(uuid);
+            }
+        }
+        Ok(players)
+    }
+
+    pub fn read(&self, uuid: &Uuid) -> Result<PlayerData, McaError> {
+        PlayerData::read(self.player_path(uuid))
+    }
+
+    pub fn write(&self, uuid: &Uuid, player: &PlayerData) -> Result<(), McaError> {
+        player.write(self.player_path(uuid))
+    }
+}
+
+#[test]
+fn test_player_round_trip() {
+    let player = PlayerData::read("fixtures/player-1.20.4.dat").unwrap();
+    assert_eq!(
+        *player.uuid(),
+        Uuid::from([0x279afc35, -397850329, -1381951053, 882502764])
+    );
+    assert_eq!(player.data_version(), 3700);
+    let inventory: Vec<_> = player
+        .inventory()
+        .iter()
+        .map(|item| (item.slot(), item.id(), item.count()))
+        .collect();
+    assert_eq!(
+        inventory,
+        [
+            (Some(0), "minecraft:diamond_sword", 1),
+            (Some(1), "minecraft:stone", 32),
+            (Some(100), "minecraft:iron_boots", 1),
+        ]
+    );
+    assert!(player.abilities().may_fly());
+    assert!(player.abilities().may_build());
+    assert!(!player.abilities().is_flying());
+
+    // Reading the rewritten file gives the same player, and the file holds exactly the tags of
+    // the original, including the ones neither `Entity` nor `PlayerTags` model.
+    let path = crate::TempPath::new("player.dat");
+    player.write(&path).unwrap();
+    let reread = PlayerData::read(&path).unwrap();
+    assert_eq!(reread.uuid(), player.uuid());
+    assert_eq!(reread.inventory().len(), 3);
+    assert!(reread.abilities().may_fly());
+    let raw = |path: &Path| {
+        let bytes = crate::dat::read_gzip_bytes(path).unwrap();
+        nbt::Blob::from_reader(&mut &bytes[..]).unwrap()
+    };
+    assert_eq!(raw(&path), raw(Path::new("fixtures/player-1.20.4.dat")));
+}
@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{bb::BoundingBox, vec::Vec3I};
+
+/// A chunk from the `poi/` folder, holding the points of interest villagers, bees and
+/// portals keep track of.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PoiChunk {
+    data_version: i32,
+    // Keyed by the section Y coordinate as a string.
+    sections: HashMap<String, PoiSection>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PoiSection {
+    records: Vec<PoiRecord>,
+    // False if the records are out of date, the game rebuilds them from the blocks when loading.
+    valid: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct PoiRecord {
+    #[serde(serialize_with = "nbt::i32_array")]
+    pos: [i32; 3],
+    // e.g. minecraft:home (beds), minecraft:armorer, minecraft:nether_portal, minecraft:bee_nest.
+    #[serde(rename = "type")]
+    typ: String,
+    // How many more villagers (or bees) can claim the POI.
+    free_tickets: i32,
+}
+
+impl PoiChunk {
+    pub fn data_version(&self) -> i32 {
+        self.data_version
+    }
+
+    /// Sections that have POI data along with their section Y coordinate.
+    pub fn sections(&self) -> impl Iterator<Item = (i32, &PoiSection)> {
+        self.sections
+            .iter()
+            .filter_map(|(y, section)| Some((y.parse().ok()?, section)))
+    }
+
+    pub fn section(&self, y: i32) -> Option<&PoiSection> {
+        self.sections.get(&y.to_string())
+    }
+
+    pub fn records(&self) -> impl Iterator<Item = &PoiRecord> {
+        self.sections
+            .values()
+            .flat_map(|section| section.records.iter())
+    }
+
+    /// Every POI of type `typ` (e.g. minecraft:cartographer) inside `area`.
+    pub fn find<'a>(
+        &'a self,
+        typ: &'a str,
+        area: &'a BoundingBox,
+    ) -> impl Iterator<Item = &'a PoiRecord> {
+        self.sections()
+            .filter(move |(y, _)| {
+                let (min_y, max_y) = (y * 16, y * 16 + 15);
+                min_y <= area.max_pos().y() && max_y >= area.min_pos().y()
+            })
+            .flat_map(|(_, section)| section.records.iter())
+            .filter(move |record| record.typ == typ && area.contains(&record.pos()))
+    }
+}
+
+impl PoiSection {
+    pub fn records(&self) -> &[PoiRecord] {
+        &self.records
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.valid
+    }
+}
+
+impl PoiRecord {
+    pub fn pos(&self) -> Vec3I {
+        Vec3I::new(self.pos[0], self.pos[1], self.pos[2])
+    }
+
+    pub fn poi_type(&self) -> &str {
+        &self.typ
+    }
+
+    pub fn free_tickets(&self) -> i32 {
+        self.free_tickets
+    }
+}
@@ -0,0 +1,91 @@
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::{mca::REGION_WIDTH, vec::Vec3I};
+
+/// Absolute chunk coordinates, the block coordinates divided by 16.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ChunkPos {
+    x: i32,
+    z: i32,
+}
+
+impl ChunkPos {
+    pub fn new(x: i32, z: i32) -> Self {
+        Self { x, z }
+    }
+
+    pub fn x(&self) -> i32 {
+        self.x
+    }
+
+    pub fn z(&self) -> i32 {
+        self.z
+    }
+
+    /// Coordinates of the region file this chunk is stored in.
+    pub fn region(&self) -> (i32, i32) {
+        (
+            self.x.div_euclid(REGION_WIDTH as i32),
+            self.z.div_euclid(REGION_WIDTH as i32),
+        )
+    }
+
+    /// Coordinates of the chunk within its region file.
+    pub fn local(&self) -> (usize, usize) {
+        (
+            self.x.rem_euclid(REGION_WIDTH as i32) as usize,
+            self.z.rem_euclid(REGION_WIDTH as i32) as usize,
+        )
+    }
+}
+
+impl fmt::Display for ChunkPos {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}, {}", self.x, self.z)
+    }
+}
+
+/// Absolute block coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+pub struct BlockPos {
+    x: i32,
+    y: i32,
+    z: i32,
+}
+
+impl BlockPos {
+    pub fn new(x: i32, y: i32, z: i32) -> Self {
+        Self { x, y, z }
+    }
+
+    pub fn x(&self) -> i32 {
+        self.x
+    }
+
+    pub fn y(&self) -> i32 {
+        self.y
+    }
+
+    pub fn z(&self) -> i32 {
+        self.z
+    }
+
+    /// The chunk the block is in.
+    pub fn chunk(&self) -> ChunkPos {
+        ChunkPos::new(self.x >> 4, self.z >> 4)
+    }
+}
+
+impl From<BlockPos> for Vec3I {
+    fn from(pos: BlockPos) -> Self {
+        Vec3I::new(pos.x, pos.y, pos.z)
+    }
+}
+
+impl fmt::Display for BlockPos {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}, {}, {}", self.x, self.y, self.z)
+    }
+}
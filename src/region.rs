@@ -1,17 +1,103 @@
-use crate::{arrays::FixedSizeArray, bb::BoundingBox, entities::BlockEntity};
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use crate::{
+    arrays::FixedSizeArray,
+    bb::BoundingBox,
+    block_iter::{BlockFilter, Blocks},
+    block_state::BlockState,
+    entities::BlockEntity,
+    heightmap::{self, HeightmapKind, COLUMNS},
+    legacy,
+    light::{LightKind, NibbleArray},
+    light_engine, nbt_serde, packed,
+    pos::ChunkPos,
+};
+use serde::{ser, Deserialize, Serialize, Serializer};
+use std::{cmp::Reverse, collections::HashMap, fmt};
 
 #[test]
-fn test_region() {
-    use crate::parse_mca;
-    let filename = "region/r.0.0.mca";
-    println!("{:#?}", parse_mca::<RegionChunk>(filename).unwrap());
+fn test_set_block() {
+    let stone = BlockStatePalette::new("minecraft:stone");
+    let mut section = Section {
+        y: 0,
+        block_states: SectionData {
+            palette: vec![BlockStatePalette::new("minecraft:air")],
+            data: None,
+        },
+        biomes: SectionData {
+            palette: vec!["minecraft:plains".to_string()],
+            data: None,
+        },
+        block_light: None,
+        sky_light: None,
+        extra: HashMap::new(),
+    };
+    section.set_block(1, 2, 3, stone.clone());
+    // Grow the palette past 16 entries so the data has to be repacked at 5 bits.
+    for i in 0..16 {
+        section.set_block(
+            i,
+            15,
+            15,
+            BlockStatePalette::new(&format!("minecraft:wool_{}", i)),
+        );
+    }
+    assert_eq!(section.block_palette().len(), 18);
+    assert_eq!(section.block_at(1, 2, 3), Some(&stone));
+    assert_eq!(section.block_at(0, 0, 0).unwrap().name(), "minecraft:air");
+    assert_eq!(
+        section.block_at(7, 15, 15).unwrap().name(),
+        "minecraft:wool_7"
+    );
+
+    for i in 0..16 {
+        section.set_block(i, 15, 15, stone.clone());
+    }
+    section.compact_palette();
+    assert_eq!(section.block_palette().len(), 2);
+    assert_eq!(section.block_at(1, 2, 3), Some(&stone));
+    assert_eq!(section.block_at(7, 15, 15), Some(&stone));
+    assert_eq!(section.block_at(0, 0, 0).unwrap().name(), "minecraft:air");
+}
+
+#[test]
+fn test_chunk_round_trip() {
+    use crate::{mca::RegionFile, nbt_serde, writer::RegionWriter, CompressionScheme};
+
+    // A 1.20.4 chunk with int and long array tags nothing models in the chunk, a section and a
+    // block entity.
+    let bytes = crate::dat::read_gzip_bytes("fixtures/chunk-1.20.4.nbt".as_ref()).unwrap();
+    let chunk: RegionChunk = nbt_serde::from_bytes(&bytes).unwrap();
+    assert!(chunk.is_light_on());
+    assert_eq!(chunk.sections().len(), 2);
+    assert_eq!(
+        chunk.section(-4).unwrap().block_at(0, 0, 0).unwrap().name(),
+        "minecraft:stone"
+    );
+    assert!(chunk.extra.contains_key("ModBiomeIds"));
+
+    let path = crate::TempPath::new("chunk-round-trip.mca");
+    let mut writer = RegionWriter::create(&path, CompressionScheme::Zlib).unwrap();
+    writer.write_chunk(0, 0, &chunk).unwrap();
+    drop(writer);
+    let written = RegionFile::open(&path)
+        .unwrap()
+        .chunk_data(0, 0)
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        nbt::Blob::from_reader(&mut &written[..]).unwrap(),
+        nbt::Blob::from_reader(&mut &bytes[..]).unwrap()
+    );
 }
 
+/// A chunk in the layout used since 1.18. Older chunks, which nest everything in a `Level`
+/// compound, are converted into this layout when they are read, choosing the schema by their
+/// `DataVersion`. This includes numeric block ids from before 1.13 and McRegion chunks. A
+/// converted chunk keeps its data version, so serializing it fails: the game would expect the
+/// old layout.
 #[derive(Debug, Deserialize, Serialize)]
-#[serde(rename_all = "PascalCase")]
+#[serde(rename_all = "PascalCase", try_from = "ChunkNbt")]
 pub struct RegionChunk {
+    #[serde(serialize_with = "serialize_data_version")]
     data_version: i32,
     #[serde(rename = "xPos")]
     x_pos: i32,
@@ -27,7 +113,9 @@ pub struct RegionChunk {
     block_entities: Option<Vec<BlockEntity>>,
     carving_masks: Option<CarvingMasks>,
     heightmaps: HeightMaps,
-    // Lights,
+    // False when the light data is missing or stale, the game then relights the chunk on load.
+    #[serde(rename = "isLightOn")]
+    is_light_on: Option<bool>,
     #[serde(rename = "fluid_ticks")]
     fluid_ticks: Vec<TileTicks>,
     #[serde(rename = "block_ticks")]
@@ -38,36 +126,743 @@ pub struct RegionChunk {
     post_processing: FixedSizeArray<Vec<i16>, 24>,
     #[serde(rename = "structures")]
     structures: Structures,
+    // Tags that aren't modelled, e.g. those added by mods, so writing the chunk keeps them.
+    #[serde(rename = "$extra", default, with = "nbt_serde::compound")]
+    extra: HashMap<String, nbt::Value>,
+}
+
+// A chunk as stored on disk, either in the 1.18 layout or with everything in `Level`. Chunks
+// with a `Level` compound have none of the 1.18 tags, those are optional or defaulted here and
+// `try_from` checks that one of the layouts is complete.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ChunkNbt {
+    // Missing before 1.9.
+    data_version: Option<i32>,
+    level: Option<legacy::Level>,
+    #[serde(rename = "xPos")]
+    x_pos: Option<i32>,
+    #[serde(rename = "zPos")]
+    z_pos: Option<i32>,
+    #[serde(rename = "yPos", default)]
+    y_pos: i32,
+    #[serde(default)]
+    status: String,
+    #[serde(default)]
+    last_update: i64,
+    #[serde(rename = "sections")]
+    sections: Option<Vec<Section>>,
+    #[serde(rename = "block_entities")]
+    block_entities: Option<Vec<BlockEntity>>,
+    carving_masks: Option<CarvingMasks>,
+    #[serde(default)]
+    heightmaps: HeightMaps,
+    #[serde(rename = "isLightOn")]
+    is_light_on: Option<bool>,
+    #[serde(rename = "fluid_ticks", default)]
+    fluid_ticks: Vec<TileTicks>,
+    #[serde(rename = "block_ticks", default)]
+    block_ticks: Vec<TileTicks>,
+    #[serde(default)]
+    inhabited_time: i64,
+    post_processing: Option<FixedSizeArray<Vec<i16>, 24>>,
+    #[serde(rename = "structures", default)]
+    structures: Structures,
+    #[serde(rename = "$extra", default, with = "nbt_serde::compound")]
+    extra: HashMap<String, nbt::Value>,
+}
+
+impl TryFrom<ChunkNbt> for RegionChunk {
+    type Error = String;
+
+    fn try_from(chunk: ChunkNbt) -> Result<Self, Self::Error> {
+        if let Some(level) = chunk.level {
+            let data_version = chunk.data_version.unwrap_or(0);
+            if data_version < legacy::FLAT_CHUNK_VERSION {
+                return Self::from_legacy(data_version, level);
+            }
+        }
+        // Entity and POI chunks, or anything else that isn't a terrain chunk, end up here.
+        let (Some(data_version), Some(x_pos), Some(z_pos), Some(sections)) =
+            (chunk.data_version, chunk.x_pos, chunk.z_pos, chunk.sections)
+        else {
+            return Err("missing Level or DataVersion, xPos, zPos and sections".to_string());
+        };
+        Ok(Self {
+            data_version,
+            x_pos,
+            z_pos,
+            y_pos: chunk.y_pos,
+            status: chunk.status,
+            last_update: chunk.last_update,
+            sections,
+            block_entities: chunk.block_entities,
+            carving_masks: chunk.carving_masks,
+            heightmaps: chunk.heightmaps,
+            is_light_on: chunk.is_light_on,
+            fluid_ticks: chunk.fluid_ticks,
+            block_ticks: chunk.block_ticks,
+            inhabited_time: chunk.inhabited_time,
+            post_processing: chunk.post_processing.unwrap_or_default(),
+            structures: chunk.structures,
+            extra: chunk.extra,
+        })
+    }
+}
+
+// Refuses to write chunks converted from an older layout, see `RegionChunk`.
+fn serialize_data_version<S>(data_version: &i32, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    if *data_version < legacy::FLAT_CHUNK_VERSION {
+        return Err(ser::Error::custom(
+            "chunk was converted from an older layout and can't be written",
+        ));
+    }
+    serializer.serialize_i32(*data_version)
+}
+
+// Blocks per section and the minimum bits per block state index.
+const SECTION_BLOCKS: usize = 4096;
+const MIN_BLOCK_BITS: u32 = 4;
+// Biomes are stored per 4x4x4 cell, so a section has 64 of them.
+const SECTION_BIOMES: usize = 64;
+const MIN_BIOME_BITS: u32 = 1;
+
+impl RegionChunk {
+    /// Version of the game that saved the chunk, see `Version::id` in level.dat.
+    pub fn data_version(&self) -> i32 {
+        self.data_version
+    }
+
+    /// Whether the chunk was converted from the layout used before 1.18.
+    pub fn is_legacy(&self) -> bool {
+        self.data_version < legacy::FLAT_CHUNK_VERSION
+    }
+
+    pub fn sections(&self) -> &[Section] {
+        &self.sections
+    }
+
+    pub fn sections_mut(&mut self) -> &mut [Section] {
+        &mut self.sections
+    }
+
+    /// The section at section Y `y`, i.e. block Y `y * 16` to `y * 16 + 15`.
+    pub fn section(&self, y: i8) -> Option<&Section> {
+        self.sections.iter().find(|section| section.y == y)
+    }
+
+    pub fn section_mut(&mut self, y: i8) -> Option<&mut Section> {
+        self.sections.iter_mut().find(|section| section.y == y)
+    }
+
+    pub fn pos(&self) -> ChunkPos {
+        ChunkPos::new(self.x_pos, self.z_pos)
+    }
+
+    /// Every block of the chunk along with its world coordinates, section by section.
+    pub fn blocks(&self) -> Blocks<'_> {
+        Blocks::new(self, BlockFilter::new())
+    }
+
+    /// The blocks matching `filter`, sections that can't contain a match are skipped without
+    /// decoding them.
+    pub fn blocks_matching(&self, filter: BlockFilter) -> Blocks<'_> {
+        Blocks::new(self, filter)
+    }
+
+    fn from_legacy(data_version: i32, mut level: legacy::Level) -> Result<Self, String> {
+        let legacy_sections = level.take_sections();
+        let biomes = level.biomes.as_deref();
+        let sections: Vec<Section> = legacy_sections
+            .into_iter()
+            .map(|mut section| -> Result<_, String> {
+                let (palette, indices) = match section.take_blocks(data_version)? {
+                    Some(blocks) => blocks,
+                    // Sections without blocks only hold light, those above and below the world
+                    // have no place in the 1.18 layout.
+                    None if (0..16).contains(&section.y) => {
+                        (vec![BlockStatePalette::new("minecraft:air")], vec![0; 4096])
+                    }
+                    None => return Ok(None),
+                };
+                let biome_names = legacy::section_biomes(biomes, section.y, data_version);
+                let mut biome_data = SectionData::default();
+                let biome_indices: Vec<u16> = biome_names
+                    .into_iter()
+                    .map(|name| biome_data.index_of(name.to_string()))
+                    .collect();
+                biome_data.store(MIN_BIOME_BITS, &biome_indices);
+                let mut block_states = SectionData {
+                    palette,
+                    data: None,
+                };
+                block_states.store(MIN_BLOCK_BITS, &indices);
+                Ok(Some(Section {
+                    y: section.y,
+                    block_states,
+                    biomes: biome_data,
+                    block_light: section.block_light,
+                    sky_light: section.sky_light,
+                    extra: HashMap::new(),
+                }))
+            })
+            .filter_map(Result::transpose)
+            .collect::<Result<_, _>>()?;
+
+        let mut heightmaps = HeightMaps::default();
+        for kind in HeightmapKind::ALL {
+            if let Some(data) = level.heightmaps.get(kind.nbt_name()) {
+                *heightmaps.get_mut(kind) =
+                    Some(WrappedLongVec(legacy::heightmap(data, data_version)));
+            }
+        }
+
+        // One list per section from the bottom of the world, padded to the 1.18 height.
+        let mut post_processing: [Vec<i16>; 24] = Default::default();
+        for (list, positions) in post_processing.iter_mut().zip(level.post_processing) {
+            *list = positions;
+        }
+
+        let mut chunk = Self {
+            data_version,
+            x_pos: level.x_pos,
+            z_pos: level.z_pos,
+            y_pos: 0,
+            status: level.status.unwrap_or_default(),
+            last_update: level.last_update,
+            sections,
+            block_entities: level.tile_entities,
+            // Older carving masks are byte arrays, they only matter while a chunk generates.
+            carving_masks: None,
+            heightmaps,
+            is_light_on: level.is_light_on,
+            fluid_ticks: level.liquid_ticks,
+            block_ticks: level.tile_ticks,
+            inhabited_time: level.inhabited_time,
+            post_processing: FixedSizeArray::from(post_processing),
+            structures: level.structures.unwrap_or_default(),
+            extra: HashMap::new(),
+        };
+        // Chunks from before 1.13 only have the old `HeightMap`, which counts light blocking
+        // blocks instead.
+        if data_version < legacy::FLATTENING_VERSION {
+            chunk.recompute_heightmaps(legacy::WORLD_HEIGHT);
+        }
+        Ok(chunk)
+    }
+
+    // An overworld chunk filled with air, for tests that need a chunk to edit.
+    #[cfg(test)]
+    pub(crate) fn empty(pos: ChunkPos) -> Self {
+        let sections = (-4..20)
+            .map(|y| Section {
+                y,
+                block_states: SectionData {
+                    palette: vec![BlockStatePalette::new("minecraft:air")],
+                    data: None,
+                },
+                biomes: SectionData {
+                    palette: vec!["minecraft:plains".to_string()],
+                    data: None,
+                },
+                block_light: None,
+                sky_light: None,
+                extra: HashMap::new(),
+            })
+            .collect();
+        Self {
+            data_version: 3700,
+            x_pos: pos.x(),
+            z_pos: pos.z(),
+            y_pos: -4,
+            status: "minecraft:full".to_string(),
+            last_update: 0,
+            sections,
+            block_entities: None,
+            carving_masks: None,
+            heightmaps: HeightMaps::default(),
+            is_light_on: None,
+            fluid_ticks: Vec::new(),
+            block_ticks: Vec::new(),
+            inhabited_time: 0,
+            post_processing: FixedSizeArray::default(),
+            structures: Structures::default(),
+            extra: HashMap::new(),
+        }
+    }
+
+    /// Lowest block Y of the chunk.
+    pub fn min_y(&self) -> i32 {
+        self.y_pos * 16
+    }
+
+    /// Decodes a heightmap into world Y values, ordered by `z * 16 + x`. Each value is the Y
+    /// just above the highest block counted by the heightmap. `height` is the height of the
+    /// chunk's dimension, see `World::dimension_height`, chunks converted from an older layout
+    /// are always 256 blocks tall. `None` if the chunk doesn't store that heightmap or it's
+    /// corrupt.
+    pub fn heightmap(&self, kind: HeightmapKind, height: i32) -> Option<[i32; COLUMNS]> {
+        let data = self.heightmaps.get(kind)?;
+        heightmap::decode(&data.0, self.min_y(), height)
+    }
+
+    /// Stores a heightmap for a dimension `height` blocks tall, see `heightmap`.
+    pub fn set_heightmap(&mut self, kind: HeightmapKind, heights: &[i32; COLUMNS], height: i32) {
+        let data = heightmap::encode(heights, self.min_y(), height);
+        *self.heightmaps.get_mut(kind) = Some(WrappedLongVec(data));
+    }
+
+    /// Rebuilds the heightmaps from the block states, e.g. after editing blocks, for a dimension
+    /// `height` blocks tall. The world generation heightmaps are only rebuilt if the chunk
+    /// already has them.
+    pub fn recompute_heightmaps(&mut self, height: i32) {
+        let min_y = self.min_y();
+        let kinds: Vec<HeightmapKind> = HeightmapKind::ALL
+            .iter()
+            .copied()
+            .filter(|kind| match kind {
+                HeightmapKind::OceanFloorWg | HeightmapKind::WorldSurfaceWg => {
+                    self.heightmaps.get(*kind).is_some()
+                }
+                _ => true,
+            })
+            .collect();
+        let mut heights = vec![[min_y; COLUMNS]; kinds.len()];
+        let mut found = vec![[false; COLUMNS]; kinds.len()];
+
+        let mut sections: Vec<&Section> = self.sections.iter().collect();
+        sections.sort_by_key(|section| Reverse(section.y));
+        for section in sections {
+            if found
+                .iter()
+                .all(|columns| columns.iter().all(|found| *found))
+            {
+                break;
+            }
+            // Classify each palette entry once instead of every block.
+            let matches: Vec<Vec<bool>> = kinds
+                .iter()
+                .map(|kind| {
+                    section
+                        .block_palette()
+                        .iter()
+                        .map(|state| kind.matches(state))
+                        .collect()
+                })
+                .collect();
+            if !matches.iter().flatten().any(|matches| *matches) {
+                continue;
+            }
+            let indices = section.decode();
+            for y in (0..16).rev() {
+                for column in 0..COLUMNS {
+                    let palette_index = indices[y * 256 + column] as usize;
+                    for (i, kind_matches) in matches.iter().enumerate() {
+                        if !found[i][column] && kind_matches.get(palette_index) == Some(&true) {
+                            found[i][column] = true;
+                            heights[i][column] = section.y as i32 * 16 + y as i32 + 1;
+                        }
+                    }
+                }
+            }
+        }
+        for (kind, heights) in kinds.into_iter().zip(heights) {
+            self.set_heightmap(kind, &heights, height);
+        }
+    }
+
+    /// Light level at the given block coordinates, X and Z are used the same way as in
+    /// `biome_at`. `None` if the section doesn't store light of that kind.
+    pub fn light_at(
+        &self,
+        kind: LightKind,
+        block_x: i32,
+        block_y: i32,
+        block_z: i32,
+    ) -> Option<u8> {
+        let section = self.section(i8::try_from(block_y.div_euclid(16)).ok()?)?;
+        section.light_at(
+            kind,
+            block_x.rem_euclid(16) as usize,
+            block_y.rem_euclid(16) as usize,
+            block_z.rem_euclid(16) as usize,
+        )
+    }
+
+    pub fn is_light_on(&self) -> bool {
+        self.is_light_on.unwrap_or(false)
+    }
+
+    pub(crate) fn set_light_on(&mut self, is_light_on: bool) {
+        self.is_light_on = Some(is_light_on);
+    }
+
+    /// Recomputes the light of this chunk on its own, see `relight_chunks` to also spread light
+    /// across chunk borders.
+    pub fn relight(&mut self, has_sky: bool) {
+        light_engine::relight_chunks([self], has_sky);
+    }
+
+    /// Removes all block and sky light and marks the chunk as unlit, so the game recomputes the
+    /// lighting the next time it loads the chunk.
+    pub fn clear_lighting(&mut self) {
+        for section in &mut self.sections {
+            section.block_light = None;
+            section.sky_light = None;
+        }
+        self.set_light_on(false);
+    }
+
+    /// Biome at the given block coordinates. X and Z may be world or chunk-local coordinates
+    /// since only their position within the chunk is used, Y is the world Y.
+    pub fn biome_at(&self, block_x: i32, block_y: i32, block_z: i32) -> Option<&str> {
+        let section = self.section(i8::try_from(block_y.div_euclid(16)).ok()?)?;
+        section.biome_at(
+            (block_x.rem_euclid(16) / 4) as usize,
+            (block_y.rem_euclid(16) / 4) as usize,
+            (block_z.rem_euclid(16) / 4) as usize,
+        )
+    }
 }
 
+/// A 16x16x16 slice of a chunk.
 #[derive(Debug, Deserialize, Serialize)]
-struct Section {
+pub struct Section {
     #[serde(rename = "Y")]
     y: i8,
     block_states: SectionData<BlockStatePalette>,
     biomes: SectionData<String>,
     #[serde(rename = "BlockLight")]
-    block_light: Option<FixedSizeArray<i8, 2048>>,
+    block_light: Option<NibbleArray>,
     #[serde(rename = "SkyLight")]
-    sky_light: Option<FixedSizeArray<i8, 2048>>,
+    sky_light: Option<NibbleArray>,
+    #[serde(rename = "$extra", default, with = "nbt_serde::compound")]
+    extra: HashMap<String, nbt::Value>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+impl Section {
+    pub fn y(&self) -> i8 {
+        self.y
+    }
+
+    pub fn block_palette(&self) -> &[BlockStatePalette] {
+        &self.block_states.palette
+    }
+
+    /// The block palette as `BlockState`s, e.g. to use them as map keys.
+    pub fn state_palette(&self) -> Vec<BlockState> {
+        self.block_states
+            .palette
+            .iter()
+            .map(BlockState::from)
+            .collect()
+    }
+
+    /// Palette index of every block in the section, ordered by `y * 256 + z * 16 + x`.
+    pub fn decode(&self) -> [u16; SECTION_BLOCKS] {
+        let mut indices = [0; SECTION_BLOCKS];
+        self.block_states.unpack(MIN_BLOCK_BITS, &mut indices);
+        indices
+    }
+
+    /// Block at section-local coordinates (0-15). `None` if the coordinates are out of range or
+    /// the data points past the end of the palette.
+    pub fn block_at(&self, x: usize, y: usize, z: usize) -> Option<&BlockStatePalette> {
+        if x >= 16 || y >= 16 || z >= 16 {
+            return None;
+        }
+        self.block_states.get(MIN_BLOCK_BITS, y * 256 + z * 16 + x)
+    }
+
+    /// Replaces the block at section-local coordinates (0-15), adding `state` to the palette
+    /// if it isn't in it yet. Palette entries that are no longer used are kept until
+    /// `compact_palette` is called.
+    ///
+    /// Panics if the coordinates are out of range.
+    pub fn set_block(&mut self, x: usize, y: usize, z: usize, state: BlockStatePalette) {
+        assert!(
+            x < 16 && y < 16 && z < 16,
+            "block {}, {}, {} is outside of the section",
+            x,
+            y,
+            z
+        );
+        self.block_states
+            .set(MIN_BLOCK_BITS, SECTION_BLOCKS, y * 256 + z * 16 + x, state);
+    }
+
+    /// Drops block states no block in the section uses any more, narrowing the data if the
+    /// palette shrinks past a power of two.
+    pub fn compact_palette(&mut self) {
+        self.block_states.compact(MIN_BLOCK_BITS, SECTION_BLOCKS);
+    }
+
+    /// Light level (0-15) at section-local coordinates (0-15). `None` if the coordinates are out
+    /// of range or the section doesn't store light of that kind.
+    pub fn light_at(&self, kind: LightKind, x: usize, y: usize, z: usize) -> Option<u8> {
+        if x >= 16 || y >= 16 || z >= 16 {
+            return None;
+        }
+        self.light(kind)
+            .as_ref()
+            .map(|light| light.get(y * 256 + z * 16 + x))
+    }
+
+    /// Sets the light level at section-local coordinates (0-15), creating an unlit array if the
+    /// section has none yet. Levels above 15 are truncated to their low 4 bits.
+    ///
+    /// Panics if the coordinates are out of range.
+    pub fn set_light(&mut self, kind: LightKind, x: usize, y: usize, z: usize, level: u8) {
+        assert!(
+            x < 16 && y < 16 && z < 16,
+            "block {}, {}, {} is outside of the section",
+            x,
+            y,
+            z
+        );
+        self.light_mut(kind)
+            .get_or_insert_with(|| NibbleArray::new(0))
+            .set(y * 256 + z * 16 + x, level);
+    }
+
+    fn light(&self, kind: LightKind) -> &Option<NibbleArray> {
+        match kind {
+            LightKind::Block => &self.block_light,
+            LightKind::Sky => &self.sky_light,
+        }
+    }
+
+    pub(crate) fn light_mut(&mut self, kind: LightKind) -> &mut Option<NibbleArray> {
+        match kind {
+            LightKind::Block => &mut self.block_light,
+            LightKind::Sky => &mut self.sky_light,
+        }
+    }
+
+    pub fn biome_palette(&self) -> &[String] {
+        &self.biomes.palette
+    }
+
+    /// Biome palette index of every 4x4x4 cell in the section, ordered by `y * 16 + z * 4 + x`.
+    pub fn decode_biomes(&self) -> [u16; SECTION_BIOMES] {
+        let mut indices = [0; SECTION_BIOMES];
+        self.biomes.unpack(MIN_BIOME_BITS, &mut indices);
+        indices
+    }
+
+    /// Biome of the cell at the given cell coordinates (0-3), e.g. `minecraft:plains`.
+    pub fn biome_at(&self, x: usize, y: usize, z: usize) -> Option<&str> {
+        if x >= 4 || y >= 4 || z >= 4 {
+            return None;
+        }
+        self.biomes
+            .get(MIN_BIOME_BITS, y * 16 + z * 4 + x)
+            .map(String::as_str)
+    }
+
+    /// Replaces the biome of the cell at the given cell coordinates (0-3), adding `biome` to the
+    /// palette if it isn't in it yet.
+    ///
+    /// Panics if the coordinates are out of range.
+    pub fn set_biome(&mut self, x: usize, y: usize, z: usize, biome: &str) {
+        assert!(
+            x < 4 && y < 4 && z < 4,
+            "cell {}, {}, {} is outside of the section",
+            x,
+            y,
+            z
+        );
+        self.biomes.set(
+            MIN_BIOME_BITS,
+            SECTION_BIOMES,
+            y * 16 + z * 4 + x,
+            biome.to_string(),
+        );
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
 struct SectionData<T> {
     palette: Vec<T>,
+    // Missing when the palette only has a single entry.
     data: Option<WrappedLongVec>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-struct WrappedLongVec(#[serde(serialize_with = "nbt::long_array")] Vec<i64>);
+impl<T> SectionData<T> {
+    fn bits_per_entry(&self, min_bits: u32) -> u32 {
+        packed::bits_per_entry(self.palette.len(), min_bits)
+    }
+
+    fn data(&self) -> &[i64] {
+        self.data
+            .as_ref()
+            .map(|data| &data.0[..])
+            .unwrap_or_default()
+    }
+
+    fn unpack(&self, min_bits: u32, indices: &mut [u16]) {
+        packed::unpack(self.data(), self.bits_per_entry(min_bits), indices);
+    }
+
+    fn get(&self, min_bits: u32, index: usize) -> Option<&T> {
+        let bits = self.bits_per_entry(min_bits);
+        self.palette
+            .get(packed::get(self.data(), bits, index) as usize)
+    }
+
+    // Palette index of `value`, adding it to the palette if it isn't in it yet.
+    fn index_of(&mut self, value: T) -> u16
+    where
+        T: PartialEq,
+    {
+        (match self.palette.iter().position(|entry| *entry == value) {
+            Some(palette_index) => palette_index,
+            None => {
+                self.palette.push(value);
+                self.palette.len() - 1
+            }
+        }) as u16
+    }
+
+    fn set(&mut self, min_bits: u32, len: usize, index: usize, value: T)
+    where
+        T: PartialEq,
+    {
+        let old_bits = self.bits_per_entry(min_bits);
+        let palette_index = self.index_of(value);
+        let bits = self.bits_per_entry(min_bits);
+        if bits == 0 {
+            return;
+        }
+        // Same width and a data array of the right size, the entry can be set in place.
+        let in_place = bits == old_bits && self.data().len() == packed::packed_len(len, bits);
+        match &mut self.data {
+            Some(data) if in_place => packed::set(&mut data.0, bits, index, palette_index),
+            _ => {
+                let mut indices = vec![0; len];
+                packed::unpack(self.data(), old_bits, &mut indices);
+                indices[index] = palette_index;
+                self.store(min_bits, &indices);
+            }
+        }
+    }
+
+    fn compact(&mut self, min_bits: u32, len: usize) {
+        if self.palette.is_empty() {
+            return;
+        }
+        let mut indices = vec![0; len];
+        self.unpack(min_bits, &mut indices);
+        let mut old_palette: Vec<Option<T>> = self.palette.drain(..).map(Some).collect();
+        let mut remapped: Vec<Option<u16>> = vec![None; old_palette.len()];
+        for index in indices.iter_mut() {
+            // Indices pointing past the palette are treated as the first entry.
+            let old_index = if (*index as usize) < old_palette.len() {
+                *index as usize
+            } else {
+                0
+            };
+            *index = match remapped[old_index] {
+                Some(new_index) => new_index,
+                None => {
+                    if let Some(entry) = old_palette[old_index].take() {
+                        self.palette.push(entry);
+                    }
+                    let new_index = (self.palette.len() - 1) as u16;
+                    remapped[old_index] = Some(new_index);
+                    new_index
+                }
+            };
+        }
+        self.store(min_bits, &indices);
+    }
+
+    // Packs palette indices at the width the current palette needs.
+    fn store(&mut self, min_bits: u32, indices: &[u16]) {
+        let bits = self.bits_per_entry(min_bits);
+        self.data = if bits == 0 {
+            None
+        } else {
+            Some(WrappedLongVec(packed::pack(indices, bits)))
+        };
+    }
+}
 
 #[derive(Debug, Deserialize, Serialize)]
+struct WrappedLongVec(#[serde(serialize_with = "nbt::i64_array")] Vec<i64>);
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(rename_all = "PascalCase")]
-struct BlockStatePalette {
+pub struct BlockStatePalette {
     name: String,
     properties: Option<HashMap<String, String>>,
 }
 
+impl BlockStatePalette {
+    /// A block state without properties.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            properties: None,
+        }
+    }
+
+    pub fn with_properties(name: &str, properties: HashMap<String, String>) -> Self {
+        Self {
+            name: name.to_string(),
+            properties: if properties.is_empty() {
+                None
+            } else {
+                Some(properties)
+            },
+        }
+    }
+
+    /// Namespaced block name, e.g. `minecraft:oak_stairs`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn properties(&self) -> Option<&HashMap<String, String>> {
+        self.properties.as_ref()
+    }
+
+    pub fn property(&self, name: &str) -> Option<&str> {
+        self.properties
+            .as_ref()
+            .and_then(|properties| properties.get(name))
+            .map(String::as_str)
+    }
+}
+
+// Formats the state the way the game writes it in commands, e.g. `minecraft:chest[type=single]`.
+// Properties are sorted by name so equal states always format the same.
+impl fmt::Display for BlockStatePalette {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if let Some(properties) = self
+            .properties
+            .as_ref()
+            .filter(|properties| !properties.is_empty())
+        {
+            let mut properties: Vec<_> = properties.iter().collect();
+            properties.sort();
+            write!(f, "[")?;
+            for (i, (name, value)) in properties.into_iter().enumerate() {
+                if i > 0 {
+                    write!(f, ",")?;
+                }
+                write!(f, "{}={}", name, value)?;
+            }
+            write!(f, "]")?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(rename_all = "UPPERCASE")]
 struct CarvingMasks {
@@ -75,7 +870,7 @@ struct CarvingMasks {
     liquid: Option<WrappedLongVec>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Default, Deserialize, Serialize)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 struct HeightMaps {
     motion_blocking: Option<WrappedLongVec>,
@@ -86,9 +881,36 @@ struct HeightMaps {
     world_surface_wg: Option<WrappedLongVec>,
 }
 
+impl HeightMaps {
+    fn get(&self, kind: HeightmapKind) -> Option<&WrappedLongVec> {
+        match kind {
+            HeightmapKind::MotionBlocking => self.motion_blocking.as_ref(),
+            HeightmapKind::MotionBlockingNoLeaves => self.motion_blocking_no_leaves.as_ref(),
+            HeightmapKind::OceanFloor => self.ocean_floor.as_ref(),
+            HeightmapKind::OceanFloorWg => self.ocean_floor_wg.as_ref(),
+            HeightmapKind::WorldSurface => self.world_surface.as_ref(),
+            HeightmapKind::WorldSurfaceWg => self.world_surface_wg.as_ref(),
+        }
+    }
+
+    fn get_mut(&mut self, kind: HeightmapKind) -> &mut Option<WrappedLongVec> {
+        match kind {
+            HeightmapKind::MotionBlocking => &mut self.motion_blocking,
+            HeightmapKind::MotionBlockingNoLeaves => &mut self.motion_blocking_no_leaves,
+            HeightmapKind::OceanFloor => &mut self.ocean_floor,
+            HeightmapKind::OceanFloorWg => &mut self.ocean_floor_wg,
+            HeightmapKind::WorldSurface => &mut self.world_surface,
+            HeightmapKind::WorldSurfaceWg => &mut self.world_surface_wg,
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize)]
-struct TileTicks {
+pub(crate) struct TileTicks {
+    #[serde(deserialize_with = "legacy::deserialize_tick_id")]
     i: String,
+    // Missing before 1.8.
+    #[serde(default)]
     p: i32,
     t: i32,
     x: i32,
@@ -96,16 +918,19 @@ struct TileTicks {
     z: i32,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-struct Structures {
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub(crate) struct Structures {
     #[serde(rename = "References")]
     references: HashMap<String, WrappedLongVec>,
+    #[serde(alias = "Starts")]
     starts: HashMap<String, StructureStart>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(rename_all = "PascalCase")]
 struct StructureStart {
+    // Missing from the `INVALID` starts older versions store for structures that didn't generate.
+    #[serde(default)]
     children: Vec<StructurePiece>,
     chunk_x: Option<i32>,
     chunk_z: Option<i32>,
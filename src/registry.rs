@@ -0,0 +1,349 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    error::Error,
+    fmt, fs,
+    io::{BufReader, Read},
+    path::Path,
+};
+
+use serde::{de::IgnoredAny, Deserialize};
+
+use crate::{
+    error::McaError,
+    region::{BlockStatePalette, RegionChunk, Section},
+};
+
+const BIOME_REGISTRY: &str = "minecraft:worldgen/biome";
+// Where the data generator puts the built-in biomes, which moved between versions.
+const BIOME_DIRS: [&str; 2] = [
+    "reports/worldgen/minecraft/worldgen/biome",
+    "data/minecraft/worldgen/biome",
+];
+
+/// Valid blocks, block properties and biomes of a game version, loaded from the reports written
+/// by the game's data generator, e.g.
+/// `java -DbundlerMainClass=net.minecraft.data.Main -jar server.jar --reports --server`.
+///
+/// The game replaces states it doesn't know with air and unknown biomes with plains when it
+/// loads a chunk, so validating edited chunks against the registry catches those mistakes early.
+#[derive(Debug, Clone, Default)]
+pub struct Registry {
+    blocks: HashMap<String, BlockInfo>,
+    biomes: HashSet<String>,
+}
+
+#[derive(Debug, Clone)]
+struct BlockInfo {
+    // Valid values of every property, in the order the game lists them.
+    properties: BTreeMap<String, Vec<String>>,
+    default: HashMap<String, String>,
+}
+
+// An entry of `blocks.json`, newer versions add fields which are ignored.
+#[derive(Deserialize)]
+struct BlockReport {
+    #[serde(default)]
+    properties: BTreeMap<String, Vec<String>>,
+    states: Vec<StateReport>,
+}
+
+#[derive(Deserialize)]
+struct StateReport {
+    #[serde(default)]
+    default: bool,
+    #[serde(default)]
+    properties: HashMap<String, String>,
+}
+
+// An entry of `registries.json`, only the names are needed.
+#[derive(Deserialize)]
+struct RegistryReport {
+    entries: HashMap<String, IgnoredAny>,
+}
+
+/// A block state or biome that isn't part of the registry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    UnknownBlock(String),
+    UnknownProperty {
+        block: String,
+        property: String,
+    },
+    InvalidValue {
+        block: String,
+        property: String,
+        value: String,
+    },
+    UnknownBiome(String),
+}
+
+impl Registry {
+    /// Loads the reports from the data generator's output directory, usually called `generated`.
+    /// `reports/blocks.json` is required, biomes are read from `reports/registries.json` and the
+    /// generated biome directories if they exist.
+    pub fn load<P: AsRef<Path>>(dir: P) -> Result<Self, McaError> {
+        let dir = dir.as_ref();
+        let blocks = dir.join("reports/blocks.json");
+        let mut registry =
+            Self::from_blocks_json(open(&blocks)?).map_err(|source| McaError::InvalidJson {
+                path: blocks.clone(),
+                source,
+            })?;
+
+        let registries = dir.join("reports/registries.json");
+        if registries.exists() {
+            let mut reports: HashMap<String, RegistryReport> =
+                serde_json::from_reader(open(&registries)?).map_err(|source| {
+                    McaError::InvalidJson {
+                        path: registries.clone(),
+                        source,
+                    }
+                })?;
+            if let Some(biomes) = reports.remove(BIOME_REGISTRY) {
+                registry.biomes.extend(biomes.entries.into_keys());
+            }
+        }
+
+        for biome_dir in BIOME_DIRS {
+            let biome_dir = dir.join(biome_dir);
+            if !biome_dir.is_dir() {
+                continue;
+            }
+            let entries = fs::read_dir(&biome_dir).map_err(|source| McaError::Io {
+                path: biome_dir.clone(),
+                source,
+            })?;
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path
+                    .extension()
+                    .is_some_and(|extension| extension == "json")
+                {
+                    if let Some(name) = path.file_stem().and_then(|name| name.to_str()) {
+                        registry.add_biome(&format!("minecraft:{}", name));
+                    }
+                }
+            }
+        }
+        Ok(registry)
+    }
+
+    /// Reads the blocks from the contents of `blocks.json`, without any biomes.
+    pub fn from_blocks_json<R: Read>(reader: R) -> serde_json::Result<Self> {
+        let reports: HashMap<String, BlockReport> = serde_json::from_reader(reader)?;
+        let blocks = reports
+            .into_iter()
+            .map(|(name, report)| {
+                let default = report
+                    .states
+                    .into_iter()
+                    .find(|state| state.default)
+                    .map(|state| state.properties)
+                    .unwrap_or_default();
+                let info = BlockInfo {
+                    properties: report.properties,
+                    default,
+                };
+                (name, info)
+            })
+            .collect();
+        Ok(Self {
+            blocks,
+            biomes: HashSet::new(),
+        })
+    }
+
+    pub fn add_biome(&mut self, name: &str) {
+        self.biomes.insert(name.to_string());
+    }
+
+    pub fn contains_block(&self, name: &str) -> bool {
+        self.blocks.contains_key(name)
+    }
+
+    pub fn contains_biome(&self, name: &str) -> bool {
+        self.biomes.contains(name)
+    }
+
+    pub fn biomes(&self) -> impl Iterator<Item = &str> {
+        self.biomes.iter().map(String::as_str)
+    }
+
+    /// Valid values of a block's property, `None` if the block or property doesn't exist.
+    pub fn property_values(&self, block: &str, property: &str) -> Option<&[String]> {
+        self.blocks
+            .get(block)
+            .and_then(|info| info.properties.get(property))
+            .map(Vec::as_slice)
+    }
+
+    /// The state a block is placed with when nothing else is specified.
+    pub fn default_state(&self, block: &str) -> Option<BlockStatePalette> {
+        let info = self.blocks.get(block)?;
+        Some(BlockStatePalette::with_properties(
+            block,
+            info.default.clone(),
+        ))
+    }
+
+    /// Checks that the block exists and every property has a valid value. Missing properties are
+    /// fine, the game fills them in with their defaults.
+    pub fn validate_state(&self, state: &BlockStatePalette) -> Result<(), ValidationError> {
+        let info = self
+            .blocks
+            .get(state.name())
+            .ok_or_else(|| ValidationError::UnknownBlock(state.name().to_string()))?;
+        for (property, value) in state.properties().into_iter().flatten() {
+            let values =
+                info.properties
+                    .get(property)
+                    .ok_or_else(|| ValidationError::UnknownProperty {
+                        block: state.name().to_string(),
+                        property: property.clone(),
+                    })?;
+            if !values.contains(value) {
+                return Err(ValidationError::InvalidValue {
+                    block: state.name().to_string(),
+                    property: property.clone(),
+                    value: value.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Validates a state and fills in the defaults of the properties it leaves out.
+    pub fn complete_state(
+        &self,
+        state: &BlockStatePalette,
+    ) -> Result<BlockStatePalette, ValidationError> {
+        self.validate_state(state)?;
+        let mut properties = state.properties().cloned().unwrap_or_default();
+        if let Some(info) = self.blocks.get(state.name()) {
+            for (property, value) in &info.default {
+                properties
+                    .entry(property.clone())
+                    .or_insert_with(|| value.clone());
+            }
+        }
+        Ok(BlockStatePalette::with_properties(state.name(), properties))
+    }
+
+    /// Validates every block palette entry and biome of a section. Biomes are only checked if
+    /// the registry has any, the reports of older versions don't list them.
+    pub fn validate_section(&self, section: &Section) -> Vec<ValidationError> {
+        let blocks = section
+            .block_palette()
+            .iter()
+            .filter_map(|state| self.validate_state(state).err());
+        let biomes = section
+            .biome_palette()
+            .iter()
+            .filter(|biome| !self.biomes.is_empty() && !self.contains_biome(biome))
+            .map(|biome| ValidationError::UnknownBiome(biome.clone()));
+        blocks.chain(biomes).collect()
+    }
+
+    /// Validates every section of a chunk, returning the errors with the Y of their section.
+    pub fn validate_chunk(&self, chunk: &RegionChunk) -> Vec<(i8, ValidationError)> {
+        chunk
+            .sections()
+            .iter()
+            .flat_map(|section| {
+                self.validate_section(section)
+                    .into_iter()
+                    .map(|err| (section.y(), err))
+            })
+            .collect()
+    }
+}
+
+fn open(path: &Path) -> Result<BufReader<fs::File>, McaError> {
+    let file = fs::File::open(path).map_err(|source| McaError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    Ok(BufReader::new(file))
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::UnknownBlock(block) => write!(f, "unknown block {}", block),
+            ValidationError::UnknownProperty { block, property } => {
+                write!(f, "{} has no property {}", block, property)
+            }
+            ValidationError::InvalidValue {
+                block,
+                property,
+                value,
+            } => write!(f, "{} is not a valid {} of {}", value, property, block),
+            ValidationError::UnknownBiome(biome) => write!(f, "unknown biome {}", biome),
+        }
+    }
+}
+
+impl Error for ValidationError {}
+
+#[test]
+fn test_registry() {
+    let blocks = r#"{
+        "minecraft:air": {"states": [{"id": 0, "default": true}]},
+        "minecraft:oak_slab": {
+            "properties": {"type": ["top", "bottom", "double"], "waterlogged": ["true", "false"]},
+            "states": [
+                {"id": 1, "properties": {"type": "top", "waterlogged": "true"}},
+                {"id": 2, "default": true, "properties": {"type": "bottom", "waterlogged": "false"}}
+            ]
+        }
+    }"#;
+    let registry = Registry::from_blocks_json(blocks.as_bytes()).unwrap();
+    let slab = |properties: &[(&str, &str)]| {
+        BlockStatePalette::with_properties(
+            "minecraft:oak_slab",
+            properties
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        )
+    };
+    assert!(registry.validate_state(&slab(&[("type", "top")])).is_ok());
+    assert!(matches!(
+        registry.validate_state(&slab(&[("type", "left")])),
+        Err(ValidationError::InvalidValue { .. })
+    ));
+    assert!(matches!(
+        registry.validate_state(&BlockStatePalette::new("minecraft:oak_stairs")),
+        Err(ValidationError::UnknownBlock(_))
+    ));
+    assert_eq!(
+        registry.complete_state(&slab(&[("type", "top")])).unwrap(),
+        slab(&[("type", "top"), ("waterlogged", "false")])
+    );
+}
+
+#[test]
+fn test_validate_section() {
+    use crate::pos::ChunkPos;
+
+    let blocks = r#"{"minecraft:air": {"states": [{"id": 0, "default": true}]}}"#;
+    let mut registry = Registry::from_blocks_json(blocks.as_bytes()).unwrap();
+    let mut chunk = RegionChunk::empty(ChunkPos::new(0, 0));
+    chunk
+        .section_mut(0)
+        .unwrap()
+        .set_biome(0, 0, 0, "minecraft:desert");
+    let section = chunk.section(0).unwrap();
+    // Without any biomes loaded they aren't checked.
+    assert_eq!(registry.validate_section(section), Vec::new());
+
+    registry.add_biome("minecraft:plains");
+    assert_eq!(
+        registry.validate_section(section),
+        vec![ValidationError::UnknownBiome(
+            "minecraft:desert".to_string()
+        )]
+    );
+    registry.add_biome("minecraft:desert");
+    assert_eq!(registry.validate_section(section), Vec::new());
+}
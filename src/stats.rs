@@ -0,0 +1,251 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+};
+
+use serde::Serialize;
+
+use crate::{
+    error::McaError,
+    region::RegionChunk,
+    world::{Dimension, StorageKind},
+};
+
+/// Counts blocks by name for every dimension and Y level, and optionally by biome as well.
+/// Useful for ore distribution curves or spotting layers of stone that were mined out.
+#[derive(Debug, Clone, Default)]
+pub struct BlockStats {
+    by_biome: bool,
+    // Keyed by dimension, block name, biome (if counting by biome) and Y.
+    counts: BTreeMap<(String, String, Option<String>, i32), u64>,
+    chunks: u64,
+}
+
+/// A single row of the statistics table.
+#[derive(Debug, Clone, Serialize)]
+pub struct BlockCount<'a> {
+    dimension: &'a str,
+    block: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    biome: Option<&'a str>,
+    y: i32,
+    count: u64,
+}
+
+impl BlockStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts blocks per biome as well as per Y level.
+    pub fn by_biome() -> Self {
+        Self {
+            by_biome: true,
+            ..Self::default()
+        }
+    }
+
+    /// Number of chunks counted so far.
+    pub fn chunks(&self) -> u64 {
+        self.chunks
+    }
+
+    /// Counts the blocks of a chunk from the dimension with the given id.
+    pub fn add_chunk(&mut self, dimension: &str, chunk: &RegionChunk) {
+        self.chunks += 1;
+        for section in chunk.sections() {
+            let palette = section.block_palette();
+            let biomes = section.biome_palette();
+            let biome_count = if self.by_biome {
+                biomes.len().max(1)
+            } else {
+                1
+            };
+            // Count palette indices per section first so names are only looked up once.
+            let mut counts = vec![0u64; palette.len() * biome_count * 16];
+            let biome_indices = section.decode_biomes();
+            let biome_at = |index: usize| {
+                if self.by_biome {
+                    let (x, y, z) = (index % 16, index / 256, (index / 16) % 16);
+                    biome_indices[(y / 4) * 16 + (z / 4) * 4 + x / 4] as usize
+                } else {
+                    0
+                }
+            };
+            let mut add = |palette_index: usize, index: usize, amount: u64| {
+                let biome = biome_at(index).min(biome_count - 1);
+                if palette_index < palette.len() {
+                    counts[(palette_index * biome_count + biome) * 16 + index / 256] += amount;
+                }
+            };
+            if palette.len() == 1 {
+                // A section holding a single state doesn't need decoding. Biomes are stored per
+                // 4x4x4 cell, so every cell holds 16 of its blocks on each of its Y levels.
+                for y in 0..16 {
+                    for z in (0..16).step_by(4) {
+                        for x in (0..16).step_by(4) {
+                            add(0, y * 256 + z * 16 + x, 16);
+                        }
+                    }
+                }
+            } else {
+                for (index, palette_index) in section.decode().iter().enumerate() {
+                    add(*palette_index as usize, index, 1);
+                }
+            }
+
+            for (i, count) in counts.into_iter().enumerate() {
+                if count == 0 {
+                    continue;
+                }
+                let (palette_index, biome, y) =
+                    (i / 16 / biome_count, (i / 16) % biome_count, i % 16);
+                let biome = if self.by_biome {
+                    Some(biomes.get(biome).cloned().unwrap_or_default())
+                } else {
+                    None
+                };
+                let key = (
+                    dimension.to_string(),
+                    palette[palette_index].name().to_string(),
+                    biome,
+                    section.y() as i32 * 16 + y as i32,
+                );
+                *self.counts.entry(key).or_insert(0) += count;
+            }
+        }
+    }
+
+    /// Counts every chunk of a dimension. Chunks that can't be read are skipped and their errors
+    /// returned so they can be reported.
+    pub fn add_dimension(&mut self, dimension: &Dimension) -> Result<Vec<McaError>, McaError> {
+        let mut errors = Vec::new();
+        for chunk in dimension.chunks::<RegionChunk>(StorageKind::Region)? {
+            match chunk {
+                Ok((_, chunk)) => self.add_chunk(dimension.id(), &chunk),
+                Err(err) => errors.push(err),
+            }
+        }
+        Ok(errors)
+    }
+
+    /// Number of `block` at `y`, summed over all dimensions and biomes.
+    pub fn count(&self, block: &str, y: i32) -> u64 {
+        self.counts
+            .iter()
+            .filter(|((_, name, _, count_y), _)| name == block && *count_y == y)
+            .map(|(_, count)| count)
+            .sum()
+    }
+
+    /// Every non-zero count, sorted by dimension, block, biome and Y.
+    pub fn rows(&self) -> impl Iterator<Item = BlockCount<'_>> {
+        self.counts
+            .iter()
+            .map(|((dimension, block, biome, y), count)| BlockCount {
+                dimension,
+                block,
+                biome: biome.as_deref(),
+                y: *y,
+                count: *count,
+            })
+    }
+
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        if self.by_biome {
+            writeln!(writer, "dimension,block,biome,y,count")?;
+        } else {
+            writeln!(writer, "dimension,block,y,count")?;
+        }
+        for row in self.rows() {
+            match row.biome {
+                Some(biome) => writeln!(
+                    writer,
+                    "{},{},{},{},{}",
+                    row.dimension, row.block, biome, row.y, row.count
+                )?,
+                None => writeln!(
+                    writer,
+                    "{},{},{},{}",
+                    row.dimension, row.block, row.y, row.count
+                )?,
+            }
+        }
+        Ok(())
+    }
+
+    pub fn write_json<W: Write>(&self, writer: W) -> serde_json::Result<()> {
+        serde_json::to_writer_pretty(writer, &self.rows().collect::<Vec<_>>())
+    }
+}
+
+impl<'a> BlockCount<'a> {
+    pub fn dimension(&self) -> &'a str {
+        self.dimension
+    }
+
+    pub fn block(&self) -> &'a str {
+        self.block
+    }
+
+    pub fn biome(&self) -> Option<&'a str> {
+        self.biome
+    }
+
+    pub fn y(&self) -> i32 {
+        self.y
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+}
+
+#[test]
+fn test_block_stats() {
+    use crate::{pos::ChunkPos, region::BlockStatePalette};
+
+    let mut chunk = RegionChunk::empty(ChunkPos::new(0, 0));
+    let section = chunk.section_mut(0).unwrap();
+    let stone = BlockStatePalette::new("minecraft:stone");
+    section.set_block(0, 0, 0, stone.clone());
+    section.set_block(5, 0, 5, stone.clone());
+    section.set_block(0, 15, 0, stone);
+    // The cell of the first block only.
+    section.set_biome(0, 0, 0, "minecraft:desert");
+    // A section of a single state in several biomes, the cell covers Y 24 to 27.
+    chunk
+        .section_mut(1)
+        .unwrap()
+        .set_biome(1, 2, 3, "minecraft:forest");
+
+    let mut stats = BlockStats::by_biome();
+    stats.add_chunk("minecraft:overworld", &chunk);
+    stats.add_chunk("minecraft:the_nether", &chunk);
+    assert_eq!(stats.count("minecraft:stone", 0), 4);
+    assert_eq!(stats.count("minecraft:stone", 15), 2);
+    assert_eq!(stats.count("minecraft:air", 0), 2 * 254);
+    // A section of a single state and biome.
+    assert_eq!(stats.count("minecraft:air", -64), 2 * 256);
+
+    let count = |block: &str, biome: &str, y: i32| {
+        stats
+            .rows()
+            .find(|row| {
+                row.dimension() == "minecraft:overworld"
+                    && row.block() == block
+                    && row.biome() == Some(biome)
+                    && row.y() == y
+            })
+            .map(|row| row.count())
+    };
+    assert_eq!(count("minecraft:stone", "minecraft:desert", 0), Some(1));
+    assert_eq!(count("minecraft:stone", "minecraft:plains", 0), Some(1));
+    assert_eq!(count("minecraft:air", "minecraft:desert", 0), Some(15));
+    assert_eq!(count("minecraft:stone", "minecraft:desert", 15), None);
+    assert_eq!(count("minecraft:air", "minecraft:plains", -64), Some(256));
+    assert_eq!(stats.count("minecraft:air", 24), 2 * 256);
+    assert_eq!(count("minecraft:air", "minecraft:forest", 24), Some(16));
+    assert_eq!(count("minecraft:air", "minecraft:plains", 27), Some(240));
+    assert_eq!(count("minecraft:air", "minecraft:forest", 28), None);
+}
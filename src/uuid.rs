@@ -1,23 +1,28 @@
 use serde::{Deserialize, Serialize};
-use std::str::FromStr;
+use std::{fmt, str::FromStr};
 use uuid::Uuid as uuid_parser;
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct Uuid([i32; 4]);
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct Uuid(#[serde(serialize_with = "nbt::i32_array")] [i32; 4]);
 
 impl Uuid {
     pub fn new(uuid: &str) -> Self {
-        let uuid = uuid_parser::from_str(uuid).unwrap();
+        Self::parse(uuid).unwrap()
+    }
+
+    /// Parses a hyphenated UUID such as the file names in `playerdata/`.
+    pub fn parse(uuid: &str) -> Option<Self> {
+        let uuid = uuid_parser::from_str(uuid).ok()?;
         let num = uuid.as_u128();
-        Self::from([
+        Some(Self::from([
             (num >> 96) as i32,
             (num >> 64) as i32,
             (num >> 32) as i32,
             (num as i32),
-        ])
+        ]))
     }
 
-    pub(crate) fn to_u128(&self) -> u128 {
+    pub(crate) fn to_u128(self) -> u128 {
         let mut bytes = [0u8; 16];
         let (left, right) = bytes.split_at_mut(8);
         let (first, second) = left.split_at_mut(4);
@@ -37,8 +42,17 @@ impl From<[i32; 4]> for Uuid {
     }
 }
 
-impl ToString for Uuid {
-    fn to_string(&self) -> String {
-        uuid_parser::from_u128(self.to_u128()).to_string()
+impl fmt::Display for Uuid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        uuid_parser::from_u128(self.to_u128()).fmt(f)
     }
 }
+
+#[test]
+fn test_uuid() {
+    let text = "279afc35-e8a9-4927-adb0-19b33499ec6c";
+    let uuid = Uuid::new(text);
+    assert_eq!(uuid.0[0], 0x279afc35);
+    assert_eq!(uuid.to_string(), text);
+    assert!(Uuid::parse("not-a-uuid").is_none());
+}
@@ -32,6 +32,20 @@ macro_rules! serialize_vec {
     };
 }
 
+// Integer vectors are stored as int arrays rather than lists, e.g. an entity chunk's `Position`.
+macro_rules! serialize_int_vec {
+    ($typ:ty) => {
+        impl Serialize for $typ {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                nbt::i32_array(&self.0, serializer)
+            }
+        }
+    };
+}
+
 macro_rules! impl_vec_getters {
     ( $typ:ty, $internal:ty, ($($var:ident),+ $(,)?)) => {
         impl $typ {
@@ -42,29 +56,26 @@ macro_rules! impl_vec_getters {
         impl_vec_getters!(@step 0usize, $typ, $internal, $($var,)*);
     };
 
-    (@step $index:expr, $typ:ty, $internal:ty, $($var:ident,)*) => {
-        $(impl_vec_getters!(@step $index + 1usize, $typ, $internal, $var);)*
-    };
-
-    (@step $index:expr, $typ:ty, $internal:ty, $var:ident) => {
+    (@step $index:expr, $typ:ty, $internal:ty, $var:ident, $($rest:ident,)*) => {
         impl $typ {
             pub fn $var(&self) -> $internal {
                 self.0[$index]
             }
         }
+        impl_vec_getters!(@step $index + 1usize, $typ, $internal, $($rest,)*);
     };
-    (@step $_index:expr) => {};
+    (@step $_index:expr, $typ:ty, $internal:ty,) => {};
 }
 
 serialize_vec!(Vec3D, 3);
 impl_vec_getters!(Vec3D, f64, (x, y, z));
 
-serialize_vec!(Vec3I, 3);
+serialize_int_vec!(Vec3I);
 impl_vec_getters!(Vec3I, i32, (x, y, z));
 
 
 serialize_vec!(Vec2F, 2);
 impl_vec_getters!(Vec2F, f32, (x, y));
 
-serialize_vec!(Vec2I, 2);
+serialize_int_vec!(Vec2I);
 impl_vec_getters!(Vec2I, i32, (x, y));
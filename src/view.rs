@@ -0,0 +1,365 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    hash::Hash,
+    path::PathBuf,
+};
+
+use crate::{
+    error::McaError,
+    mca::{CompressionScheme, RegionFile},
+    pos::ChunkPos,
+    region::{BlockStatePalette, RegionChunk},
+    world::Dimension,
+    writer::RegionWriter,
+};
+
+const DEFAULT_CHUNK_CACHE_SIZE: usize = 256;
+// Region files are read into memory whole, so only a few are kept around.
+const REGION_CACHE_SIZE: usize = 4;
+
+/// Block access in absolute block coordinates across a whole dimension. Chunks are loaded on
+/// demand and kept in a least recently used cache, edited chunks stay in memory until `commit`
+/// writes them back to their region files.
+///
+/// Editing blocks leaves heightmaps and light stale, see `RegionChunk::recompute_heightmaps`
+/// and `RegionChunk::relight` which can be run through `chunk_mut` before committing.
+pub struct WorldView {
+    dimension: Dimension,
+    compression_scheme: CompressionScheme,
+    // `None` for chunks that haven't been generated.
+    chunks: Lru<ChunkPos, Option<RegionChunk>>,
+    regions: Lru<(i32, i32), Option<RegionFile>>,
+    dirty: HashSet<ChunkPos>,
+}
+
+impl WorldView {
+    pub fn new(dimension: Dimension) -> Self {
+        Self {
+            dimension,
+            compression_scheme: CompressionScheme::Zlib,
+            chunks: Lru::new(DEFAULT_CHUNK_CACHE_SIZE),
+            regions: Lru::new(REGION_CACHE_SIZE),
+            dirty: HashSet::new(),
+        }
+    }
+
+    /// Number of unedited chunks kept in memory, edited chunks are kept until `commit` on top
+    /// of these.
+    pub fn set_cache_size(&mut self, cache_size: usize) {
+        self.chunks.capacity = cache_size.max(1);
+    }
+
+    /// Compression used for chunks written by `commit`, zlib by default like the game.
+    pub fn set_compression_scheme(&mut self, compression_scheme: CompressionScheme) {
+        self.compression_scheme = compression_scheme;
+    }
+
+    pub fn dimension(&self) -> &Dimension {
+        &self.dimension
+    }
+
+    /// Block at the given world coordinates, `None` if the chunk hasn't been generated or `y`
+    /// is outside of the world.
+    pub fn get_block(
+        &mut self,
+        x: i32,
+        y: i32,
+        z: i32,
+    ) -> Result<Option<&BlockStatePalette>, McaError> {
+        let chunk = match self.chunk(ChunkPos::new(x >> 4, z >> 4))? {
+            Some(chunk) => chunk,
+            None => return Ok(None),
+        };
+        let section = i8::try_from(y >> 4)
+            .ok()
+            .and_then(|section_y| chunk.section(section_y));
+        Ok(section.and_then(|section| {
+            section.block_at((x & 15) as usize, (y & 15) as usize, (z & 15) as usize)
+        }))
+    }
+
+    /// Replaces the block at the given world coordinates. Returns false, leaving the world
+    /// untouched, if the chunk hasn't been generated or `y` is outside of the world.
+    pub fn set_block(
+        &mut self,
+        x: i32,
+        y: i32,
+        z: i32,
+        state: BlockStatePalette,
+    ) -> Result<bool, McaError> {
+        let pos = ChunkPos::new(x >> 4, z >> 4);
+        self.load(pos)?;
+        let section = match i8::try_from(y >> 4).ok().and_then(|section_y| {
+            self.chunks
+                .get_mut(&pos)
+                .and_then(Option::as_mut)
+                .and_then(|chunk| chunk.section_mut(section_y))
+        }) {
+            Some(section) => section,
+            None => return Ok(false),
+        };
+        section.set_block(
+            (x & 15) as usize,
+            (y & 15) as usize,
+            (z & 15) as usize,
+            state,
+        );
+        self.dirty.insert(pos);
+        self.chunks.pin(&pos);
+        Ok(true)
+    }
+
+    /// The chunk at the given chunk coordinates, `None` if it hasn't been generated.
+    pub fn chunk(&mut self, pos: ChunkPos) -> Result<Option<&RegionChunk>, McaError> {
+        self.load(pos)?;
+        Ok(self.chunks.get(&pos).and_then(Option::as_ref))
+    }
+
+    /// Mutable access to a chunk, the chunk is written back on the next `commit`.
+    pub fn chunk_mut(&mut self, pos: ChunkPos) -> Result<Option<&mut RegionChunk>, McaError> {
+        self.load(pos)?;
+        if self.chunks.peek(&pos).is_some_and(Option::is_some) {
+            self.dirty.insert(pos);
+            self.chunks.pin(&pos);
+        }
+        Ok(self.chunks.get_mut(&pos).and_then(Option::as_mut))
+    }
+
+    /// Writes every edited chunk back to its region file. Chunks converted from a layout older
+    /// than 1.18 can't be written, if any was edited nothing is written.
+    pub fn commit(&mut self) -> Result<(), McaError> {
+        let mut dirty: Vec<ChunkPos> = self.dirty.iter().copied().collect();
+        dirty.sort_by_key(|pos| pos.region());
+        for pos in &dirty {
+            let chunk = self.chunks.peek(pos).and_then(Option::as_ref);
+            if chunk.is_some_and(RegionChunk::is_legacy) {
+                return Err(McaError::LegacyChunk {
+                    path: self.region_path(pos.region()),
+                    chunk: *pos,
+                });
+            }
+        }
+        let mut writer: Option<((i32, i32), RegionWriter)> = None;
+        for pos in &dirty {
+            // Chunks stay dirty until they're written, so a later commit can retry after an error.
+            let chunk = match self.chunks.peek(pos).and_then(Option::as_ref) {
+                Some(chunk) => chunk,
+                None => {
+                    self.dirty.remove(pos);
+                    self.chunks.unpin(pos);
+                    continue;
+                }
+            };
+            let region = pos.region();
+            if writer.as_ref().map(|(coordinates, _)| *coordinates) != Some(region) {
+                writer = Some((
+                    region,
+                    RegionWriter::open(self.region_path(region), self.compression_scheme)?,
+                ));
+                // The cached copy of the region file no longer matches what's on disk.
+                self.regions.remove(&region);
+            }
+            if let Some((_, writer)) = &mut writer {
+                let (local_x, local_z) = pos.local();
+                writer.write_chunk(local_x, local_z, chunk)?;
+            }
+            self.dirty.remove(pos);
+            self.chunks.unpin(pos);
+        }
+        Ok(())
+    }
+
+    /// Drops all edits that haven't been committed yet.
+    pub fn discard(&mut self) {
+        for pos in self.dirty.drain() {
+            self.chunks.remove(&pos);
+        }
+    }
+
+    fn load(&mut self, pos: ChunkPos) -> Result<(), McaError> {
+        if self.chunks.get(&pos).is_some() {
+            return Ok(());
+        }
+        let region = pos.region();
+        if self.regions.get(&region).is_none() {
+            let path = self.region_path(region);
+            let region_file = if path.exists() {
+                Some(RegionFile::open(path)?)
+            } else {
+                None
+            };
+            self.regions.insert(region, region_file);
+        }
+        let (local_x, local_z) = pos.local();
+        let chunk = match self.regions.get(&region).and_then(Option::as_ref) {
+            Some(region_file) => region_file.get_chunk::<RegionChunk>(local_x, local_z)?,
+            None => None,
+        };
+        self.chunks.insert(pos, chunk);
+        Ok(())
+    }
+
+    fn region_path(&self, (x, z): (i32, i32)) -> PathBuf {
+        self.dimension
+            .region_dir()
+            .join(format!("r.{}.{}.mca", x, z))
+    }
+}
+
+// A small least recently used cache, evicting the entry that was used longest ago once it grows
+// past its capacity. Pinned entries are never evicted and don't count towards the capacity.
+struct Lru<K, V> {
+    capacity: usize,
+    // The value and when it was last used, `None` while the entry is pinned.
+    entries: HashMap<K, (V, Option<u64>)>,
+    // Unpinned entries by when they were last used, the first one is evicted next.
+    order: BTreeMap<u64, K>,
+    clock: u64,
+}
+
+impl<K, V> Lru<K, V>
+where
+    K: Eq + Hash + Clone,
+{
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            clock: 0,
+        }
+    }
+
+    fn get(&mut self, key: &K) -> Option<&V> {
+        self.get_mut(key).map(|value| &*value)
+    }
+
+    // Looks up an entry without counting it as used.
+    fn peek(&self, key: &K) -> Option<&V> {
+        self.entries.get(key).map(|(value, _)| value)
+    }
+
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.clock += 1;
+        let clock = self.clock;
+        let (value, last_used) = self.entries.get_mut(key)?;
+        if let Some(last_used) = last_used {
+            self.order.remove(last_used);
+            self.order.insert(clock, key.clone());
+            *last_used = clock;
+        }
+        Some(value)
+    }
+
+    fn insert(&mut self, key: K, value: V) {
+        self.remove(&key);
+        self.clock += 1;
+        self.entries.insert(key.clone(), (value, Some(self.clock)));
+        self.order.insert(self.clock, key);
+        self.evict();
+    }
+
+    // Keeps the entry until it's unpinned, whatever the capacity.
+    fn pin(&mut self, key: &K) {
+        if let Some((_, last_used)) = self.entries.get_mut(key) {
+            if let Some(last_used) = last_used.take() {
+                self.order.remove(&last_used);
+            }
+        }
+    }
+
+    // Makes a pinned entry evictable again, as if it was just used.
+    fn unpin(&mut self, key: &K) {
+        if let Some((_, last_used @ None)) = self.entries.get_mut(key) {
+            self.clock += 1;
+            *last_used = Some(self.clock);
+            self.order.insert(self.clock, key.clone());
+            self.evict();
+        }
+    }
+
+    fn remove(&mut self, key: &K) {
+        if let Some((_, Some(last_used))) = self.entries.remove(key) {
+            self.order.remove(&last_used);
+        }
+    }
+
+    fn evict(&mut self) {
+        while self.order.len() > self.capacity {
+            if let Some((_, oldest)) = self.order.pop_first() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_lru_eviction() {
+    let mut lru = Lru::new(2);
+    lru.insert(1, "a");
+    lru.insert(2, "b");
+    lru.get(&1);
+    lru.insert(3, "c");
+    assert_eq!(lru.get(&1), Some(&"a"));
+    assert_eq!(lru.get(&2), None);
+    // Pinned entries don't count towards the capacity.
+    lru.pin(&1);
+    lru.insert(4, "d");
+    lru.insert(5, "e");
+    assert_eq!(lru.get(&1), Some(&"a"));
+    assert_eq!(lru.get(&3), None);
+    assert_eq!(lru.get(&4), Some(&"d"));
+    // Once unpinned it counts as just used, so 5, used longest ago, is evicted instead.
+    lru.unpin(&1);
+    assert_eq!(lru.peek(&5), None);
+    assert_eq!(lru.peek(&1), Some(&"a"));
+    assert_eq!(lru.peek(&4), Some(&"d"));
+}
+
+#[test]
+fn test_world_view_commit() {
+    use crate::world::World;
+
+    let world = crate::TempPath::new("world");
+    std::fs::create_dir_all(world.join("region")).unwrap();
+    // Chunk -1, -1 is the last chunk of region -1, -1 and holds x and z from -16 to -1.
+    let mut writer =
+        RegionWriter::create(world.join("region/r.-1.-1.mca"), CompressionScheme::Zlib).unwrap();
+    writer
+        .write_chunk(31, 31, &RegionChunk::empty(ChunkPos::new(-1, -1)))
+        .unwrap();
+    drop(writer);
+    let dimension = World::open(&world)
+        .unwrap()
+        .dimension("overworld")
+        .unwrap()
+        .clone();
+
+    let stone = BlockStatePalette::new("minecraft:stone");
+    let mut view = WorldView::new(dimension.clone());
+    assert!(view.set_block(-1, -1, -1, stone.clone()).unwrap());
+    assert!(view.set_block(-16, -64, -16, stone.clone()).unwrap());
+    // Chunk -1, -2 and region 0, 0 don't exist and -65 is below the world.
+    assert!(!view.set_block(-1, -1, -17, stone.clone()).unwrap());
+    assert!(!view.set_block(0, 0, 0, stone.clone()).unwrap());
+    assert!(!view.set_block(-1, -65, -1, stone.clone()).unwrap());
+
+    let chunk = view.chunk(ChunkPos::new(-1, -1)).unwrap().unwrap();
+    assert_eq!(
+        chunk.section(-1).unwrap().block_at(15, 15, 15),
+        Some(&stone)
+    );
+    assert_eq!(chunk.section(-4).unwrap().block_at(0, 0, 0), Some(&stone));
+    assert_eq!(
+        view.get_block(-2, -1, -1)
+            .unwrap()
+            .map(|state| state.name()),
+        Some("minecraft:air")
+    );
+    view.commit().unwrap();
+    assert!(view.dirty.is_empty());
+
+    let mut view = WorldView::new(dimension);
+    assert_eq!(view.get_block(-1, -1, -1).unwrap(), Some(&stone));
+    assert_eq!(view.get_block(-16, -64, -16).unwrap(), Some(&stone));
+}
@@ -0,0 +1,533 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::de::DeserializeOwned;
+
+use crate::{
+    bb::BoundingBox,
+    block_iter::{BlockFilter, ChunksBlocks},
+    error::McaError,
+    level::LevelData,
+    mca::{region_coordinates, Chunks, RegionFile, REGION_WIDTH},
+    player::PlayerDataStore,
+    poi::{PoiChunk, PoiRecord},
+    pos::{BlockPos, ChunkPos},
+    region::{BlockStatePalette, RegionChunk},
+};
+
+/// The kinds of region files a dimension stores, each in its own folder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StorageKind {
+    // Terrain, block entities and structures (`RegionChunk`).
+    Region,
+    // Entities (`EntityChunk`).
+    Entities,
+    // Points of interest such as beds and job sites.
+    Poi,
+}
+
+impl StorageKind {
+    pub fn directory_name(&self) -> &'static str {
+        match self {
+            StorageKind::Region => "region",
+            StorageKind::Entities => "entities",
+            StorageKind::Poi => "poi",
+        }
+    }
+}
+
+/// A region file found in a storage folder, along with the region coordinates from its name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegionPath {
+    x: i32,
+    z: i32,
+    path: PathBuf,
+}
+
+impl RegionPath {
+    pub fn x(&self) -> i32 {
+        self.x
+    }
+
+    pub fn z(&self) -> i32 {
+        self.z
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn open(&self) -> Result<RegionFile, McaError> {
+        RegionFile::open(&self.path)
+    }
+}
+
+/// A single dimension of a world, such as `minecraft:overworld`.
+#[derive(Debug, Clone)]
+pub struct Dimension {
+    id: String,
+    path: PathBuf,
+}
+
+impl Dimension {
+    /// Namespaced id of the dimension, e.g. `minecraft:the_nether`.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Folder holding the `region/`, `entities/` and `poi/` folders of the dimension.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn storage_dir(&self, kind: StorageKind) -> PathBuf {
+        self.path.join(kind.directory_name())
+    }
+
+    pub fn region_dir(&self) -> PathBuf {
+        self.storage_dir(StorageKind::Region)
+    }
+
+    pub fn entities_dir(&self) -> PathBuf {
+        self.storage_dir(StorageKind::Entities)
+    }
+
+    pub fn poi_dir(&self) -> PathBuf {
+        self.storage_dir(StorageKind::Poi)
+    }
+
+    /// Every `r.<x>.<z>.mca` file of the given kind, sorted by region coordinates.
+    /// A dimension that hasn't stored anything of that kind yet has no region files.
+    ///
+    /// Worlds from before 1.2 store McRegion `r.<x>.<z>.mcr` files instead. The game leaves them
+    /// behind when it converts a world, so they are only listed if the folder has no `.mca`
+    /// files at all. Otherwise a region deleted since the conversion would come back from its
+    /// stale `.mcr`.
+    pub fn region_files(&self, kind: StorageKind) -> Result<Vec<RegionPath>, McaError> {
+        let directory = self.storage_dir(kind);
+        let entries = match fs::read_dir(&directory) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(source) => {
+                return Err(McaError::Io {
+                    path: directory,
+                    source,
+                })
+            }
+        };
+        let mut region_files = Vec::new();
+        for entry in entries {
+            let path = match entry {
+                Ok(entry) => entry.path(),
+                Err(source) => {
+                    return Err(McaError::Io {
+                        path: directory,
+                        source,
+                    })
+                }
+            };
+            if let Some((x, z)) = region_coordinates(&path) {
+                region_files.push(RegionPath { x, z, path });
+            }
+        }
+        let is_mcregion = |region: &RegionPath| {
+            region
+                .path
+                .extension()
+                .is_some_and(|extension| extension == "mcr")
+        };
+        if !region_files.iter().all(is_mcregion) {
+            region_files.retain(|region| !is_mcregion(region));
+        }
+        region_files.sort_by_key(|region| (region.x, region.z));
+        Ok(region_files)
+    }
+
+    /// Lazily decodes every chunk of the given kind in the dimension, one region file at a time.
+    /// Errors are yielded per chunk (or per region file if it can't be opened) so a scan can
+    /// carry on past them.
+    pub fn chunks<T>(&self, kind: StorageKind) -> Result<DimensionChunks<T>, McaError>
+    where
+        T: DeserializeOwned,
+    {
+        Ok(DimensionChunks {
+            region_files: self.region_files(kind)?.into_iter(),
+            current: None,
+        })
+    }
+
+    /// The blocks in the dimension matching `filter`, region file by region file.
+    pub fn blocks(
+        &self,
+        filter: BlockFilter,
+    ) -> Result<ChunksBlocks<impl Iterator<Item = Result<RegionChunk, McaError>>>, McaError> {
+        let chunks = self
+            .chunks::<RegionChunk>(StorageKind::Region)?
+            .map(|chunk| chunk.map(|(_, chunk)| chunk));
+        Ok(ChunksBlocks::new(chunks, filter))
+    }
+
+    /// Every POI of type `typ` (e.g. minecraft:meeting for bells) inside `area`. Only the
+    /// POI chunks overlapping the area are read.
+    pub fn find_pois(&self, typ: &str, area: &BoundingBox) -> Result<Vec<PoiRecord>, McaError> {
+        let (min, max) = (area.min_pos(), area.max_pos());
+        let min_chunk = ChunkPos::new(min.x() >> 4, min.z() >> 4);
+        let max_chunk = ChunkPos::new(max.x() >> 4, max.z() >> 4);
+        let (min_region, max_region) = (min_chunk.region(), max_chunk.region());
+        let region_width = REGION_WIDTH as i32;
+
+        let mut records = Vec::new();
+        for region_x in min_region.0..=max_region.0 {
+            for region_z in min_region.1..=max_region.1 {
+                let path = self
+                    .poi_dir()
+                    .join(format!("r.{}.{}.mca", region_x, region_z));
+                if !path.exists() {
+                    continue;
+                }
+                let region = RegionFile::open(path)?;
+                let chunks_x = min_chunk.x().max(region_x * region_width)
+                    ..=max_chunk
+                        .x()
+                        .min(region_x * region_width + region_width - 1);
+                for chunk_x in chunks_x {
+                    let chunks_z = min_chunk.z().max(region_z * region_width)
+                        ..=max_chunk
+                            .z()
+                            .min(region_z * region_width + region_width - 1);
+                    for chunk_z in chunks_z {
+                        let (local_x, local_z) = ChunkPos::new(chunk_x, chunk_z).local();
+                        if let Some(chunk) = region.get_chunk::<PoiChunk>(local_x, local_z)? {
+                            records.extend(chunk.find(typ, area).cloned());
+                        }
+                    }
+                }
+            }
+        }
+        Ok(records)
+    }
+}
+
+/// Iterator over the chunks of every region file in a dimension, created by `Dimension::chunks`.
+pub struct DimensionChunks<T> {
+    region_files: std::vec::IntoIter<RegionPath>,
+    current: Option<Chunks<RegionFile, T>>,
+}
+
+impl<T> Iterator for DimensionChunks<T>
+where
+    T: DeserializeOwned,
+{
+    type Item = Result<(ChunkPos, T), McaError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(chunks) = &mut self.current {
+                if let Some((pos, chunk)) = chunks.next() {
+                    return Some(chunk.map(|chunk| (pos, chunk)));
+                }
+            }
+            match self.region_files.next()?.open() {
+                Ok(region) => self.current = Some(region.into_chunks()),
+                Err(err) => {
+                    self.current = None;
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+/// A world save folder and the dimensions found in it.
+#[derive(Debug, Clone)]
+pub struct World {
+    path: PathBuf,
+    dimensions: Vec<Dimension>,
+}
+
+impl World {
+    /// Finds the overworld, the nether (`DIM-1`), the end (`DIM1`) and any datapack dimensions
+    /// stored under `dimensions/<namespace>/<name>`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, McaError> {
+        let path = path.as_ref().to_path_buf();
+        if !path.is_dir() {
+            return Err(McaError::Io {
+                source: io::Error::new(io::ErrorKind::NotFound, "world folder not found"),
+                path,
+            });
+        }
+        let mut dimensions = Vec::new();
+        for (id, dimension_path) in [
+            ("minecraft:overworld", path.clone()),
+            ("minecraft:the_nether", path.join("DIM-1")),
+            ("minecraft:the_end", path.join("DIM1")),
+        ] {
+            if has_storage(&dimension_path) {
+                dimensions.push(Dimension {
+                    id: id.to_string(),
+                    path: dimension_path,
+                });
+            }
+        }
+        for (id, dimension_path) in datapack_dimensions(&path.join("dimensions"))? {
+            if has_storage(&dimension_path) && !dimensions.iter().any(|other| other.id == id) {
+                dimensions.push(Dimension {
+                    id,
+                    path: dimension_path,
+                });
+            }
+        }
+        Ok(Self { path, dimensions })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Reads the world's `level.dat`.
+    pub fn level_data(&self) -> Result<LevelData, McaError> {
+        LevelData::read(self.path.join("level.dat"))
+    }
+
+    /// The world's `playerdata/` folder.
+    pub fn player_data(&self) -> PlayerDataStore {
+        PlayerDataStore::new(self.path.join("playerdata"))
+    }
+
+    pub fn dimensions(&self) -> &[Dimension] {
+        &self.dimensions
+    }
+
+    /// Whether a dimension has sky light, e.g. to pass on to `relight_chunks`. Vanilla only
+    /// leaves it out in the nether and the end, other dimensions are looked up through their
+    /// dimension type in `level.dat` and the folders in `datapacks/`. `None` if the dimension
+    /// type can't be found, e.g. because its data pack is zipped.
+    pub fn has_sky_light(&self, dimension: &Dimension) -> Result<Option<bool>, McaError> {
+        self.dimension_type_property(dimension, vanilla_sky_light, |json| {
+            json.get("has_skylight")?.as_bool()
+        })
+    }
+
+    /// Height of a dimension in blocks, e.g. to pass on to `RegionChunk::heightmap`. Looked up
+    /// the same way as `has_sky_light`, for the layout used since 1.18.
+    pub fn dimension_height(&self, dimension: &Dimension) -> Result<Option<i32>, McaError> {
+        self.dimension_type_property(dimension, vanilla_height, |json| {
+            i32::try_from(json.get("height")?.as_i64()?).ok()
+        })
+    }
+
+    // Reads a property of a dimension's type, `vanilla` gives it for the built-in dimension
+    // types and `property` reads it from a dimension type's JSON.
+    fn dimension_type_property<T>(
+        &self,
+        dimension: &Dimension,
+        vanilla: fn(&str) -> Option<T>,
+        property: fn(&serde_json::Value) -> Option<T>,
+    ) -> Result<Option<T>, McaError> {
+        if let Some(value) = vanilla(dimension.id()) {
+            return Ok(Some(value));
+        }
+        let level = self.level_data()?;
+        // Data packs later in the list take priority.
+        let data_packs: Vec<PathBuf> = level
+            .enabled_data_packs()
+            .iter()
+            .rev()
+            .filter_map(|name| name.strip_prefix("file/"))
+            .map(|name| self.path.join("datapacks").join(name))
+            .filter(|path| path.is_dir())
+            .collect();
+        let generator = level
+            .world_gen_settings()
+            .and_then(|settings| settings.dimensions().get(dimension.id()));
+        let dimension_type = match generator {
+            Some(generator) => generator.dimension_type().to_string(),
+            None => {
+                let json = data_pack_json(&data_packs, "dimension", dimension.id())?;
+                match json.as_ref().and_then(|json| json.get("type")?.as_str()) {
+                    Some(dimension_type) => dimension_type.to_string(),
+                    None => return Ok(None),
+                }
+            }
+        };
+        if let Some(value) = vanilla(&dimension_type) {
+            return Ok(Some(value));
+        }
+        let json = data_pack_json(&data_packs, "dimension_type", &dimension_type)?;
+        Ok(json.as_ref().and_then(property))
+    }
+
+    /// Looks up a dimension by id, the `minecraft:` namespace can be left out.
+    pub fn dimension(&self, id: &str) -> Option<&Dimension> {
+        self.dimensions.iter().find(|dimension| {
+            dimension.id == id || dimension.id.strip_prefix("minecraft:") == Some(id)
+        })
+    }
+
+    /// The blocks matching `filter` across every dimension, along with the dimension they are in.
+    pub fn blocks(
+        &self,
+        filter: BlockFilter,
+    ) -> Result<
+        impl Iterator<Item = Result<(&Dimension, BlockPos, BlockStatePalette), McaError>>,
+        McaError,
+    > {
+        let mut dimensions = Vec::new();
+        for dimension in &self.dimensions {
+            let blocks = dimension.blocks(filter.clone())?;
+            dimensions
+                .push(blocks.map(move |block| block.map(|(pos, state)| (dimension, pos, state))));
+        }
+        Ok(dimensions.into_iter().flatten())
+    }
+
+    /// Region files of the given kind across every dimension.
+    pub fn region_files(
+        &self,
+        kind: StorageKind,
+    ) -> Result<Vec<(&Dimension, RegionPath)>, McaError> {
+        let mut region_files = Vec::new();
+        for dimension in &self.dimensions {
+            for region_path in dimension.region_files(kind)? {
+                region_files.push((dimension, region_path));
+            }
+        }
+        Ok(region_files)
+    }
+}
+
+// A folder counts as a dimension once the game has saved any region files into it.
+fn has_storage(path: &Path) -> bool {
+    [StorageKind::Region, StorageKind::Entities, StorageKind::Poi]
+        .iter()
+        .any(|kind| path.join(kind.directory_name()).is_dir())
+}
+
+// Datapack dimensions live in `dimensions/<namespace>/<name>`, nested folders are allowed
+// in the name (`dimensions/foo/bar/baz` is `foo:bar/baz`).
+fn datapack_dimensions(path: &Path) -> Result<Vec<(String, PathBuf)>, McaError> {
+    let mut dimensions = Vec::new();
+    for namespace in sub_directories(path)? {
+        let namespace_name = file_name(&namespace);
+        let mut pending = sub_directories(&namespace)?;
+        while let Some(dimension_path) = pending.pop() {
+            if has_storage(&dimension_path) {
+                let name = dimension_path
+                    .strip_prefix(&namespace)
+                    .unwrap_or(&dimension_path)
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                dimensions.push((format!("{}:{}", namespace_name, name), dimension_path));
+            } else {
+                pending.extend(sub_directories(&dimension_path)?);
+            }
+        }
+    }
+    dimensions.sort();
+    Ok(dimensions)
+}
+
+// Sky light of the vanilla dimensions and dimension types, which share their ids.
+fn vanilla_sky_light(id: &str) -> Option<bool> {
+    match id {
+        "minecraft:overworld" | "minecraft:overworld_caves" => Some(true),
+        "minecraft:the_nether" | "minecraft:the_end" => Some(false),
+        _ => None,
+    }
+}
+
+fn vanilla_height(id: &str) -> Option<i32> {
+    match id {
+        "minecraft:overworld" | "minecraft:overworld_caves" => Some(384),
+        "minecraft:the_nether" | "minecraft:the_end" => Some(256),
+        _ => None,
+    }
+}
+
+// Reads `data/<namespace>/<kind>/<name>.json` from the first data pack folder that has it.
+fn data_pack_json(
+    data_packs: &[PathBuf],
+    kind: &str,
+    id: &str,
+) -> Result<Option<serde_json::Value>, McaError> {
+    let (namespace, name) = id.split_once(':').unwrap_or(("minecraft", id));
+    for data_pack in data_packs {
+        let path = data_pack
+            .join("data")
+            .join(namespace)
+            .join(kind)
+            .join(format!("{}.json", name));
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(source) => return Err(McaError::Io { path, source }),
+        };
+        return serde_json::from_slice(&bytes)
+            .map(Some)
+            .map_err(|source| McaError::InvalidJson { path, source });
+    }
+    Ok(None)
+}
+
+fn sub_directories(path: &Path) -> Result<Vec<PathBuf>, McaError> {
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(source) => {
+            return Err(McaError::Io {
+                path: path.to_path_buf(),
+                source,
+            })
+        }
+    };
+    let mut directories = Vec::new();
+    for entry in entries {
+        match entry {
+            Ok(entry) if entry.path().is_dir() => directories.push(entry.path()),
+            Ok(_) => {}
+            Err(source) => {
+                return Err(McaError::Io {
+                    path: path.to_path_buf(),
+                    source,
+                })
+            }
+        }
+    }
+    Ok(directories)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+#[test]
+fn test_mcregion_fallback() {
+    let path = crate::TempPath::new("dimension");
+    let region_dir = path.join("region");
+    fs::create_dir_all(&region_dir).unwrap();
+    let dimension = Dimension {
+        id: "minecraft:overworld".to_string(),
+        path: path.to_path_buf(),
+    };
+    let names = |dimension: &Dimension| {
+        dimension
+            .region_files(StorageKind::Region)
+            .unwrap()
+            .iter()
+            .map(|region| file_name(region.path()))
+            .collect::<Vec<_>>()
+    };
+    for name in ["r.0.0.mcr", "r.1.0.mcr"] {
+        fs::write(region_dir.join(name), b"").unwrap();
+    }
+    assert_eq!(names(&dimension), ["r.0.0.mcr", "r.1.0.mcr"]);
+    // Once the world has been converted, stale `.mcr` files are ignored.
+    fs::write(region_dir.join("r.0.0.mca"), b"").unwrap();
+    assert_eq!(names(&dimension), ["r.0.0.mca"]);
+}
@@ -0,0 +1,338 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+
+use crate::{
+    error::McaError,
+    mca::{
+        chunk_pos, compress_bytes_with_scheme, external_chunk_path, CompressionScheme,
+        CHUNKS_PER_REGION, EXTERNAL_CHUNK_FLAG, HEADER_SIZE, REGION_WIDTH, SECTOR_SIZE,
+    },
+    nbt_serde,
+};
+
+// The sector count of a chunk is stored in a single byte.
+const MAX_CHUNK_SECTORS: usize = 255;
+
+/// Writes chunks into a region file, updating the location and timestamp tables as it goes.
+pub struct RegionWriter {
+    path: PathBuf,
+    file: File,
+    // Location entries: the sector offset in the upper 24 bits and the sector count in the lower 8.
+    locations: Vec<u32>,
+    timestamps: Vec<u32>,
+    compression_scheme: CompressionScheme,
+}
+
+impl RegionWriter {
+    /// Creates an empty region file, truncating any existing file at `path`.
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        compression_scheme: CompressionScheme,
+    ) -> Result<Self, McaError> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .and_then(|mut file| file.write_all(&[0u8; HEADER_SIZE]).map(|_| file));
+        match file {
+            Ok(file) => Ok(Self {
+                path,
+                file,
+                locations: vec![0; CHUNKS_PER_REGION],
+                timestamps: vec![0; CHUNKS_PER_REGION],
+                compression_scheme,
+            }),
+            Err(source) => Err(McaError::Io { path, source }),
+        }
+    }
+
+    /// Opens a region file so individual chunks can be replaced without rewriting the rest of it.
+    /// The file is created if it doesn't exist yet.
+    pub fn open<P: AsRef<Path>>(
+        path: P,
+        compression_scheme: CompressionScheme,
+    ) -> Result<Self, McaError> {
+        let path = path.as_ref().to_path_buf();
+        let (file, header) = match read_header(&path) {
+            Ok(Some(file_and_header)) => file_and_header,
+            Ok(None) => return Err(McaError::TruncatedHeader { path }),
+            Err(source) => return Err(McaError::Io { path, source }),
+        };
+        let read_u32 = |offset: usize| {
+            u32::from_be_bytes([
+                header[offset],
+                header[offset + 1],
+                header[offset + 2],
+                header[offset + 3],
+            ])
+        };
+        let locations = (0..CHUNKS_PER_REGION)
+            .map(|index| read_u32(index * 4))
+            .collect();
+        let timestamps = (0..CHUNKS_PER_REGION)
+            .map(|index| read_u32(SECTOR_SIZE + index * 4))
+            .collect();
+        Ok(Self {
+            path,
+            file,
+            locations,
+            timestamps,
+            compression_scheme,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Serializes and compresses `chunk`, then stores it at the given region-local coordinates.
+    /// If the chunk still fits in the sectors it already occupies it is overwritten in place.
+    /// A `RegionChunk` converted from a layout older than 1.18 fails to serialize, which is
+    /// returned as `McaError::Nbt`.
+    pub fn write_chunk<T>(
+        &mut self,
+        local_x: usize,
+        local_z: usize,
+        chunk: &T,
+    ) -> Result<(), McaError>
+    where
+        T: Serialize,
+    {
+        let index = self.chunk_index(local_x, local_z)?;
+        let nbt_bytes = nbt_serde::to_bytes(chunk).map_err(|source| McaError::Nbt {
+            path: self.path.clone(),
+            chunk: chunk_pos(&self.path, local_x, local_z),
+            source,
+        })?;
+        let compressed = compress_bytes_with_scheme(&nbt_bytes, self.compression_scheme)
+            .map_err(|source| self.io_error(source))?;
+        self.write_compressed_chunk(index, &compressed, current_timestamp())
+    }
+
+    /// Removes a chunk from the region, the game will regenerate it the next time it is loaded.
+    pub fn remove_chunk(&mut self, local_x: usize, local_z: usize) -> Result<(), McaError> {
+        let index = self.chunk_index(local_x, local_z)?;
+        self.remove_external_chunk(index)?;
+        self.set_header_entry(index, 0, 0)
+            .map_err(|source| self.io_error(source))
+    }
+
+    fn write_compressed_chunk(
+        &mut self,
+        index: usize,
+        compressed: &[u8],
+        timestamp: u32,
+    ) -> Result<(), McaError> {
+        let compression_scheme: u8 = self.compression_scheme.into();
+        // Chunk header: 4 byte length (which counts the compression byte) followed by the compression scheme.
+        let mut sector_bytes = Vec::with_capacity(compressed.len() + 5);
+        sector_bytes.extend_from_slice(&(compressed.len() as u32 + 1).to_be_bytes());
+        sector_bytes.push(compression_scheme);
+        sector_bytes.extend_from_slice(compressed);
+        let mut sectors = sector_bytes.len().div_ceil(SECTOR_SIZE);
+        if sectors > MAX_CHUNK_SECTORS {
+            // Too big for the region file, the data goes into a .mcc file and the region only
+            // keeps a header with the external flag set.
+            self.write_external_chunk(index, compressed)?;
+            sector_bytes.clear();
+            sector_bytes.extend_from_slice(&1u32.to_be_bytes());
+            sector_bytes.push(compression_scheme | EXTERNAL_CHUNK_FLAG);
+            sectors = 1;
+        } else {
+            self.remove_external_chunk(index)?;
+        }
+        // Pad the chunk out to a whole number of sectors.
+        sector_bytes.resize(sectors * SECTOR_SIZE, 0);
+
+        let (current_offset, current_sectors) = self.location(index);
+        let offset = if current_offset != 0 && sectors <= current_sectors {
+            current_offset
+        } else {
+            self.allocate_sectors(index, sectors)
+        };
+        self.timestamps[index] = timestamp;
+        self.file
+            .seek(SeekFrom::Start((offset * SECTOR_SIZE) as u64))
+            .and_then(|_| self.file.write_all(&sector_bytes))
+            .and_then(|_| self.set_header_entry(index, offset, sectors))
+            .map_err(|source| self.io_error(source))
+    }
+
+    fn write_external_chunk(&self, index: usize, compressed: &[u8]) -> Result<(), McaError> {
+        let (local_x, local_z) = (index % REGION_WIDTH, index / REGION_WIDTH);
+        let chunk = chunk_pos(&self.path, local_x, local_z);
+        let path = external_chunk_path(&self.path, local_x, local_z).ok_or_else(|| {
+            McaError::UnknownRegionCoordinates {
+                path: self.path.clone(),
+                chunk,
+            }
+        })?;
+        // Write to a temporary file first so a crash never leaves a half written chunk behind.
+        let temporary_path = path.with_extension("mcc.tmp");
+        fs::write(&temporary_path, compressed)
+            .and_then(|_| fs::rename(temporary_path, path))
+            .map_err(|source| McaError::ExternalChunk {
+                path: self.path.clone(),
+                chunk,
+                source,
+            })
+    }
+
+    // Chunks that shrink back below the size limit no longer need their .mcc file.
+    fn remove_external_chunk(&self, index: usize) -> Result<(), McaError> {
+        let (local_x, local_z) = (index % REGION_WIDTH, index / REGION_WIDTH);
+        let path = match external_chunk_path(&self.path, local_x, local_z) {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        match fs::remove_file(path) {
+            Err(source) if source.kind() != io::ErrorKind::NotFound => {
+                Err(McaError::ExternalChunk {
+                    path: self.path.clone(),
+                    chunk: chunk_pos(&self.path, local_x, local_z),
+                    source,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn location(&self, index: usize) -> (usize, usize) {
+        let location = self.locations[index];
+        ((location >> 8) as usize, (location & 0xFF) as usize)
+    }
+
+    // Finds the first run of free sectors big enough for the chunk at `index`, ignoring the
+    // sectors the chunk currently occupies since they are about to be freed.
+    fn allocate_sectors(&self, index: usize, sectors: usize) -> usize {
+        let mut used = vec![true; HEADER_SIZE / SECTOR_SIZE];
+        for other in (0..CHUNKS_PER_REGION).filter(|other| *other != index) {
+            let (offset, count) = self.location(other);
+            if offset == 0 || count == 0 {
+                continue;
+            }
+            if used.len() < offset + count {
+                used.resize(offset + count, false);
+            }
+            used[offset..offset + count].fill(true);
+        }
+        let mut start = 0;
+        for (sector, in_use) in used.iter().enumerate() {
+            if *in_use {
+                start = sector + 1;
+            } else if sector + 1 - start == sectors {
+                return start;
+            }
+        }
+        // No gap was big enough, append the chunk to the end of the file.
+        start
+    }
+
+    fn set_header_entry(&mut self, index: usize, offset: usize, sectors: usize) -> io::Result<()> {
+        let location = if sectors == 0 {
+            0
+        } else {
+            ((offset as u32) << 8) | sectors as u32
+        };
+        self.locations[index] = location;
+        if location == 0 {
+            self.timestamps[index] = 0;
+        }
+        self.file.seek(SeekFrom::Start((index * 4) as u64))?;
+        self.file.write_all(&location.to_be_bytes())?;
+        self.file
+            .seek(SeekFrom::Start((SECTOR_SIZE + index * 4) as u64))?;
+        self.file.write_all(&self.timestamps[index].to_be_bytes())
+    }
+
+    fn chunk_index(&self, local_x: usize, local_z: usize) -> Result<usize, McaError> {
+        if local_x >= REGION_WIDTH || local_z >= REGION_WIDTH {
+            return Err(McaError::ChunkOutOfRange {
+                path: self.path.clone(),
+                local_x,
+                local_z,
+            });
+        }
+        Ok(local_x + local_z * REGION_WIDTH)
+    }
+
+    fn io_error(&self, source: io::Error) -> McaError {
+        McaError::Io {
+            path: self.path.clone(),
+            source,
+        }
+    }
+}
+
+// Opens the region file and reads its header, an empty file gets a fresh header written to it.
+// Returns `None` if the file is too small to hold a header.
+fn read_header(path: &Path) -> io::Result<Option<(File, Vec<u8>)>> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)?;
+    let mut header = vec![0u8; HEADER_SIZE];
+    let length = file.metadata()?.len() as usize;
+    if length == 0 {
+        file.write_all(&header)?;
+    } else if length < HEADER_SIZE {
+        return Ok(None);
+    } else {
+        file.read_exact(&mut header)?;
+    }
+    Ok(Some((file, header)))
+}
+
+fn current_timestamp() -> u32 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as u32)
+        .unwrap_or(0)
+}
+
+#[test]
+fn test_write_and_replace_chunk() {
+    use crate::mca::RegionFile;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, Serialize, PartialEq)]
+    struct TestChunk {
+        name: String,
+        values: Vec<i32>,
+    }
+
+    let path = crate::TempPath::new("writer-test.mca");
+    let small = TestChunk {
+        name: "small".into(),
+        values: vec![1, 2, 3],
+    };
+    // Large enough to no longer fit in the sector of the first chunk.
+    let large = TestChunk {
+        name: "large".into(),
+        values: (0..10_000u32)
+            .map(|i| i.wrapping_mul(2_654_435_761) as i32)
+            .collect(),
+    };
+
+    let mut writer = RegionWriter::create(&path, CompressionScheme::Zlib).unwrap();
+    writer.write_chunk(0, 0, &small).unwrap();
+    writer.write_chunk(31, 31, &small).unwrap();
+    writer.write_chunk(0, 0, &large).unwrap();
+    drop(writer);
+
+    let region = RegionFile::open(&path).unwrap();
+    assert_eq!(region.get_chunk::<TestChunk>(0, 0).unwrap(), Some(large));
+    assert_eq!(region.get_chunk::<TestChunk>(31, 31).unwrap(), Some(small));
+    assert!(!region.has_chunk(1, 0));
+}